@@ -0,0 +1,125 @@
+use nih_plug::prelude::*;
+use rand::Rng;
+
+use crate::random::{gaussian, Prng};
+
+/// How the time between two triggers is distributed around the trigger interval.
+#[derive(Enum, PartialEq, Clone, Copy)]
+pub enum Density {
+  /// Synchronous, every trigger exactly one interval apart.
+  Periodic,
+  /// Quasi-synchronous, every interval offset by up to the deviation.
+  Jittered,
+  /// Asynchronous, triggers at random with the interval as the average.
+  Poisson,
+  /// Quasi-synchronous, intervals spread around the interval by the deviation.
+  Gaussian,
+}
+
+impl Density {
+  /// Length of the next interval, relative to the trigger interval.
+  #[inline]
+  fn next(&self, deviation: f32, rng: &mut Prng) -> f32 {
+    let deviation = deviation.clamp(0.0, 1.0);
+    let factor = match self {
+      Density::Periodic => { 1.0 },
+      Density::Jittered => { 1.0 + deviation * rng.gen_range(-1.0..=1.0) },
+      // exponentially distributed intervals give a Poisson distributed number of triggers
+      Density::Poisson  => { -(1.0 - rng.gen::<f32>()).ln() },
+      Density::Gaussian => { 1.0 + deviation * gaussian(rng) },
+    };
+    // never let two triggers land on the same sample
+    factor.max(0.01)
+  }
+}
+
+/// Trigger with a selectable distribution of the time between triggers.
+pub struct Cloud {
+  phase: f32,
+  factor: f32,
+  samplerate: f32,
+  rng: Prng,
+}
+
+impl Cloud {
+  pub fn new() -> Self {
+    Self { phase: 1.0, factor: 1.0, samplerate: 0.0, rng: Prng::new(0) }
+  }
+
+  /// Returns `1.0` on every trigger, on average once every `interval` seconds.
+  ///
+  /// `deviation` in the range `(0.0, 1.0)` is the spread of the jittered and gaussian intervals,
+  /// relative to `interval`. Changes to `interval` apply to the interval already running.
+  #[inline]
+  pub fn play(&mut self, interval: f32, density: Density, deviation: f32) -> f32 {
+    self.phase += 1.0 / (interval * self.factor * self.samplerate).max(1.0);
+    if self.phase < 1.0 { return 0.0 }
+    self.phase -= 1.0;
+    self.factor = density.next(deviation, &mut self.rng);
+    1.0
+  }
+
+  #[inline]
+  pub fn set_samplerate(&mut self, samplerate: f32) {
+    self.samplerate = samplerate;
+  }
+
+  #[inline]
+  pub fn reseed(&mut self, seed: u64) {
+    self.rng.reseed(seed);
+  }
+
+  /// Triggers on the next sample.
+  #[inline]
+  pub fn reset(&mut self) {
+    self.phase = 1.0;
+    self.factor = 1.0;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SR: f32 = 1000.0;
+
+  /// Sample indices of the triggers over ten seconds, with a 100 ms interval.
+  fn triggers(density: Density, deviation: f32) -> Vec<usize> {
+    let mut cloud = Cloud::new();
+    cloud.set_samplerate(SR);
+    (0..10000).filter(|_| cloud.play(0.1, density, deviation) >= 1.0).collect()
+  }
+
+  fn intervals(triggers: &[usize]) -> Vec<usize> {
+    triggers.windows(2).map(|w| w[1] - w[0]).collect()
+  }
+
+  #[test]
+  fn periodic_is_within_a_sample() {
+    let t = triggers(Density::Periodic, 1.0);
+    assert_eq!(t[0], 0);
+    // the fractional phase may land a trigger one sample either side
+    assert!(intervals(&t).iter().all(|i| (99..=101).contains(i)));
+  }
+
+  #[test]
+  fn jittered_stays_within_deviation() {
+    let t = triggers(Density::Jittered, 0.5);
+    assert!(intervals(&t).iter().all(|i| (49..=151).contains(i)));
+    assert!(intervals(&t).iter().any(|i| *i != 100));
+  }
+
+  #[test]
+  fn without_deviation_gaussian_is_periodic() {
+    assert!(triggers(Density::Gaussian, 0.0) == triggers(Density::Periodic, 0.0));
+  }
+
+  #[test]
+  fn poisson_keeps_the_average() {
+    let mut cloud = Cloud::new();
+    cloud.set_samplerate(SR);
+    let count = (0..1_000_000).filter(|_| cloud.play(0.1, Density::Poisson, 0.0) >= 1.0).count();
+    // ten thousand triggers on average, the standard deviation is a hundred
+    assert!((9500..=10500).contains(&count));
+  }
+}
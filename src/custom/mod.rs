@@ -0,0 +1,185 @@
+use std::sync::{
+  RwLock,
+  RwLockReadGuard,
+  atomic::{AtomicU32, Ordering},
+};
+use nih_plug::params::persist::PersistentField;
+use serde::{Deserialize, Serialize};
+
+/// Maximum number of breakpoints in a custom shape.
+pub const MAX_POINTS: usize = 32;
+
+/// A breakpoint, `x` is the position in the cycle `(0.0, 1.0)`, `y` the value `(-1.0, 1.0)`.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct Point {
+  pub x: f32,
+  pub y: f32,
+}
+
+impl Point {
+  #[inline]
+  fn clamped(self) -> Self {
+    Self {
+      x: self.x.clamp(0.0, 1.0 - f32::EPSILON),
+      y: self.y.clamp(-1.0, 1.0),
+    }
+  }
+}
+
+/// User drawn LFO shape, persisted in the plugin state.
+///
+/// The editor writes the breakpoints, and the audio thread renders them into a table
+/// whenever `version` changes.
+pub struct CustomShape {
+  points: RwLock<Vec<Point>>,
+  version: AtomicU32,
+}
+
+impl Default for CustomShape {
+  fn default() -> Self {
+    Self {
+      points: RwLock::new(vec![
+        Point { x: 0.0,  y: 0.0 },
+        Point { x: 0.25, y: 1.0 },
+        Point { x: 0.75, y: -1.0 },
+      ]),
+      version: AtomicU32::new(1),
+    }
+  }
+}
+
+impl CustomShape {
+  #[inline]
+  pub fn version(&self) -> u32 {
+    self.version.load(Ordering::Acquire)
+  }
+
+  /// Does not block, returns `None` while the editor is writing.
+  #[inline]
+  pub fn try_points(&self) -> Option<RwLockReadGuard<Vec<Point>>> {
+    self.points.try_read().ok()
+  }
+
+  pub fn points(&self) -> Vec<Point> {
+    self.points.read().unwrap().clone()
+  }
+
+  /// Edits the breakpoints, they are kept sorted, in range and at most `MAX_POINTS` long.
+  pub fn update(&self, f: impl FnOnce(&mut Vec<Point>)) {
+    let mut points = self.points.write().unwrap();
+    f(&mut points);
+    sanitize(&mut points);
+    self.version.fetch_add(1, Ordering::Release);
+  }
+
+  /// Adds a breakpoint, returns its index or `None` if the shape is full.
+  pub fn insert(&self, point: Point) -> Option<usize> {
+    let point = point.clamped();
+    self.update(|points| {
+      if points.len() < MAX_POINTS { points.push(point); }
+    });
+    self.points.read().unwrap().iter().position(|p| *p == point)
+  }
+
+  /// Moves a breakpoint, returns its new index since it may pass its neighbours.
+  pub fn move_point(&self, index: usize, point: Point) -> usize {
+    let point = point.clamped();
+    self.update(|points| {
+      if let Some(p) = points.get_mut(index) { *p = point; }
+    });
+    self.points.read().unwrap().iter().position(|p| *p == point).unwrap_or(index)
+  }
+
+  /// Removes a breakpoint, the last one is always kept.
+  pub fn remove(&self, index: usize) {
+    self.update(|points| {
+      if points.len() > 1 && index < points.len() { points.remove(index); }
+    });
+  }
+}
+
+impl<'a> PersistentField<'a, Vec<Point>> for CustomShape {
+  fn set(&self, new_value: Vec<Point>) {
+    self.update(|points| *points = new_value);
+  }
+
+  fn map<F, R>(&self, f: F) -> R
+  where
+    F: Fn(&Vec<Point>) -> R,
+  {
+    f(&self.points.read().unwrap())
+  }
+}
+
+fn sanitize(points: &mut Vec<Point>) {
+  points.iter_mut().for_each(|p| *p = p.clamped());
+  points.sort_by(|a, b| a.x.total_cmp(&b.x));
+  points.truncate(MAX_POINTS);
+  if points.is_empty() {
+    points.push(Point { x: 0.0, y: 0.0 });
+  }
+}
+
+/// Renders the breakpoints into `table`, linearly interpolated and wrapping around
+/// from the last point to the first. `points` must be sorted, does not allocate.
+pub fn render(points: &[Point], table: &mut [f32]) {
+  let (first, last) = match (points.first(), points.last()) {
+    (Some(first), Some(last)) => (*first, *last),
+    _ => { table.fill(0.0); return }
+  };
+  let len = table.len() as f32;
+  let mut next = 0;
+  for (i, sample) in table.iter_mut().enumerate() {
+    let x = i as f32 / len;
+    while next < points.len() && points[next].x <= x {
+      next += 1;
+    }
+    // segment around x, the ends borrow the points from the neighbouring cycles
+    let a = if next == 0 { Point { x: last.x - 1.0, y: last.y } } else { points[next - 1] };
+    let b = if next == points.len() { Point { x: first.x + 1.0, y: first.y } } else { points[next] };
+    let width = b.x - a.x;
+    *sample = if width <= 0.0 { a.y } else { a.y + (b.y - a.y) * (x - a.x) / width };
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn renders_through_points() {
+    let points = [
+      Point { x: 0.0, y: 0.0 },
+      Point { x: 0.25, y: 1.0 },
+      Point { x: 0.75, y: -1.0 },
+    ];
+    let mut table = [0.0; 1024];
+    render(&points, &mut table);
+    assert_eq!(table[0], 0.0);
+    assert_eq!(table[256], 1.0);
+    assert_eq!(table[512], 0.0);
+    assert_eq!(table[768], -1.0);
+    // wraps from the last point back to the first
+    assert_eq!(table[896], -0.5);
+  }
+
+  #[test]
+  fn single_point_is_constant() {
+    let mut table = [1.0; 64];
+    render(&[Point { x: 0.5, y: 0.3 }], &mut table);
+    assert!(table.iter().all(|s| *s == 0.3));
+  }
+
+  #[test]
+  fn update_sanitizes() {
+    let shape = CustomShape::default();
+    let version = shape.version();
+    shape.update(|points| {
+      points.clear();
+      points.push(Point { x: 0.9, y: 3.0 });
+      points.push(Point { x: -1.0, y: 0.0 });
+    });
+    assert!(shape.version() != version);
+    assert_eq!(shape.points(), vec![Point { x: 0.0, y: 0.0 }, Point { x: 0.9, y: 1.0 }]);
+  }
+}
@@ -0,0 +1,182 @@
+use std::sync::{Mutex, atomic::Ordering};
+use atomic_float::AtomicF32;
+
+use crate::scale::NAMES;
+
+/// Length of the analysis window, the lowest detectable pitch is two samples per half window.
+const WINDOW: usize = 2048;
+/// Highest detectable pitch, in Hz.
+const MAX_FREQUENCY: f32 = 2000.0;
+/// Windows quieter than this, in RMS, are skipped.
+const SILENCE: f32 = 0.01;
+/// YIN threshold of the normalized difference, lower is stricter.
+const THRESHOLD: f32 = 0.15;
+/// Samples copied from the audio thread per buffer.
+const CHUNK: usize = 16384;
+
+/// Pitch of the captured buffer.
+///
+/// The audio thread copies the buffer a chunk at a time once a capture is done, and a
+/// background task runs the detector on the copy.
+pub struct Detector {
+  buffer: Mutex<Vec<f32>>,
+  samplerate: AtomicF32,
+  /// Detected fundamental in Hz, `0.0` when nothing has been detected.
+  frequency: AtomicF32,
+}
+
+impl Detector {
+  pub fn new(len: usize) -> Self {
+    Self {
+      buffer: Mutex::new(vec![0.0; len]),
+      samplerate: AtomicF32::new(48000.0),
+      frequency: AtomicF32::new(0.0),
+    }
+  }
+
+  pub fn set_samplerate(&self, samplerate: f32) {
+    self.samplerate.store(samplerate, Ordering::Relaxed);
+  }
+
+  #[inline]
+  pub fn frequency(&self) -> Option<f32> {
+    Some(self.frequency.load(Ordering::Relaxed)).filter(|f| *f > 0.0)
+  }
+
+  /// Copies the chunk of a finished capture starting at `from`, and returns where the next
+  /// one starts, or `None` once the whole capture is copied. While the last capture is still
+  /// being analyzed nothing is copied and `from` is returned. Does not block or allocate.
+  #[inline]
+  pub fn copy(&self, buffer: &[f32], from: usize) -> Option<usize> {
+    match self.buffer.try_lock() {
+      Ok(mut copy) => {
+        let len = copy.len().min(buffer.len());
+        let to = (from + CHUNK).min(len);
+        copy[from..to].copy_from_slice(&buffer[from..to]);
+        Some(to).filter(|to| *to < len)
+      },
+      Err(_) => { Some(from) }
+    }
+  }
+
+  /// Runs the detector on the last capture, on the background thread.
+  pub fn run(&self) {
+    let buffer = self.buffer.lock().unwrap();
+    let frequency = detect(&buffer, self.samplerate.load(Ordering::Relaxed));
+    self.frequency.store(frequency.unwrap_or(0.0), Ordering::Relaxed);
+  }
+
+  /// The detected note and how far off it is, relative to `a4`.
+  pub fn describe(&self, a4: f32) -> String {
+    match self.frequency() {
+      Some(frequency) => { describe(frequency, a4) },
+      None            => { "no pitch".to_string() }
+    }
+  }
+}
+
+/// Note name with octave, and the offset in cents.
+pub fn describe(frequency: f32, a4: f32) -> String {
+  let note = 69.0 + 12.0 * (frequency / a4).log2();
+  let key = note.round() as i32;
+  let cents = ((note - key as f32) * 100.0).round();
+  format!("{}{} {:+.0} ct", NAMES[key.rem_euclid(12) as usize], key.div_euclid(12) - 1, cents)
+}
+
+/// Fundamental of `buffer`, the median over the voiced windows.
+pub fn detect(buffer: &[f32], samplerate: f32) -> Option<f32> {
+  let mut difference = vec![0.0; WINDOW / 2];
+  let mut notes = buffer
+    .chunks_exact(WINDOW)
+    .filter(|frame| (frame.iter().map(|x| x * x).sum::<f32>() / WINDOW as f32).sqrt() > SILENCE)
+    .filter_map(|frame| yin(frame, samplerate, &mut difference))
+    // octave errors are symmetric around the median in the log domain
+    .map(f32::log2)
+    .collect::<Vec<_>>();
+  if notes.is_empty() { return None }
+  notes.sort_by(f32::total_cmp);
+  Some(notes[notes.len() / 2].exp2())
+}
+
+/// YIN estimate of the fundamental of `frame`, `None` if it is not periodic enough.
+/// `difference` is scratch space of half the frame length.
+fn yin(frame: &[f32], samplerate: f32, difference: &mut [f32]) -> Option<f32> {
+  let half = frame.len() / 2;
+  let min_lag = ((samplerate / MAX_FREQUENCY) as usize).max(2);
+  // difference function
+  for (lag, d) in difference.iter_mut().enumerate().take(half) {
+    *d = (0..half).map(|j| frame[j] - frame[j + lag]).map(|x| x * x).sum();
+  }
+  // cumulative mean normalized difference
+  difference[0] = 1.0;
+  let mut sum = 0.0;
+  for lag in 1..half {
+    sum += difference[lag];
+    difference[lag] = if sum > 0.0 { difference[lag] * lag as f32 / sum } else { 1.0 };
+  }
+  // first dip below the threshold, followed down to its minimum
+  let mut lag = (min_lag..half - 1).find(|lag| difference[*lag] < THRESHOLD)?;
+  while lag + 1 < half && difference[lag + 1] < difference[lag] {
+    lag += 1;
+  }
+  // parabolic interpolation around the minimum
+  let (a, b, c) = (difference[lag - 1], difference[lag], difference[(lag + 1).min(half - 1)]);
+  let curve = a + c - 2.0 * b;
+  let offset = if curve.abs() > f32::EPSILON { 0.5 * (a - c) / curve } else { 0.0 };
+  Some(samplerate / (lag as f32 + offset))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn tone(frequency: f32, len: usize) -> Vec<f32> {
+    (0..len)
+      .map(|i| {
+        let phase = std::f32::consts::TAU * frequency * i as f32 / 48000.0;
+        // a few harmonics, the second one louder than the fundamental
+        0.3 * phase.sin() + 0.4 * (2.0 * phase).sin() + 0.1 * (3.0 * phase).sin()
+      })
+      .collect()
+  }
+
+  #[test]
+  fn detects_fundamental() {
+    for frequency in [82.41, 220.0, 261.63, 880.0] {
+      let detected = detect(&tone(frequency, 48000), 48000.0).unwrap();
+      assert!((1200.0 * (detected / frequency).log2()).abs() < 5.0, "{} {}", frequency, detected);
+    }
+  }
+
+  #[test]
+  fn silence_and_noise() {
+    assert_eq!(detect(&[0.0; 48000], 48000.0), None);
+    let mut rng = crate::random::Prng::new(3);
+    let noise = (0..48000).map(|_| rand::Rng::gen_range(&mut rng, -1.0..1.0)).collect::<Vec<f32>>();
+    assert_eq!(detect(&noise, 48000.0), None);
+  }
+
+  #[test]
+  fn copies_in_chunks() {
+    let capture = tone(220.0, 48000);
+    let detector = Detector::new(48000);
+    detector.set_samplerate(48000.0);
+    let mut copies = 0;
+    let mut from = Some(0);
+    while let Some(f) = from {
+      from = detector.copy(&capture, f);
+      copies += 1;
+    }
+    assert_eq!(copies, 48000 / CHUNK + 1);
+    detector.run();
+    assert!((detector.frequency().unwrap() - 220.0).abs() < 1.0);
+  }
+
+  #[test]
+  fn names() {
+    assert_eq!(describe(440.0, 440.0), "A4 +0 ct");
+    assert_eq!(describe(261.63, 440.0), "C4 +0 ct");
+    assert_eq!(describe(432.0, 440.0), "A4 -32 ct");
+    assert_eq!(describe(432.0, 432.0), "A4 +0 ct");
+  }
+}
@@ -16,6 +16,7 @@ where
       .height(height)
       .child_left(Stretch(1.0))
       .child_right(Stretch(1.0))
-      .class(name);
+      // labels with spaces become dashed class names, "auto gain" is styled by `.auto-gain`
+      .class(&name.replace(' ', "-"));
       
 }
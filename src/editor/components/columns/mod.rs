@@ -1,3 +1,4 @@
+use nih_plug_vizia::widgets::ParamSlider;
 use nih_plug_vizia::vizia::prelude::*;
 
 use crate::editor::Data;
@@ -20,6 +21,12 @@ pub fn left_col(cx: &mut Context) {
     create_slider(cx, "duration", Data::params, LH, LW, SH, SW, |params| &params.duration);
     create_slider(cx, "trigger",  Data::params, LH, LW, SH, SW, |params| &params.trigger);
     create_slider(cx, "stereo spread",   Data::params, LH, LW, SH, SW, |params| &params.spread);
+    HStack::new(cx, |cx| {
+      create_slider(cx, "dry/wet", Data::params, LH, LW, SH, SW, |params| &params.mix);
+      create_slider(cx, "gain",    Data::params, LH, LW, SH, SW, |params| &params.gain);
+    })
+      .height(Auto)
+      .col_between(Pixels(12.0));
     // VStack::new(cx, |cx| {
     // });
   })
@@ -33,20 +40,22 @@ pub fn left_col(cx: &mut Context) {
 
 pub fn right_col(cx: &mut Context) {
   VStack::new(cx, |cx| {
-    create_slider(cx, "rate",       Data::params, LH, LW, SH, SW, |params| &params.rate);
-    create_slider(cx, "mod amount", Data::params, LH, LW, SH, SW, |params| &params.rate_mod_amount);
+    HStack::new(cx, |cx| {
+      create_slider(cx, "coarse",    Data::params, LH, LW, SH, SW, |params| &params.coarse);
+      create_slider(cx, "fine",      Data::params, LH, LW, SH, SW, |params| &params.fine);
+      create_slider(cx, "direction", Data::params, LH, LW, SH, SW, |params| &params.direction);
+      create_slider(cx, "reverse",   Data::params, LH, LW, SH, SW, |params| &params.reverse);
+    })
+      .height(Auto)
+      .col_between(Pixels(12.0));
+    create_slider(cx, "mod depth",  Data::params, LH, LW, SH, SW, |params| &params.rate_mod_depth);
     create_slider(cx, "mod freq",   Data::params, LH, LW, SH, SW, |params| &params.rate_mod_freq);
     create_slider(cx, "mod shape",  Data::params, LH, LW, SH, SW, |params| &params.rate_mod_shape);
   VStack::new(cx, |cx| {
       HStack::new(cx, |cx| {
-        create_button(
-          cx, 
-          "random",
-          Data::params,
-          BH,
-          BW,
-          |params| &params.random
-        );
+        ParamSlider::new(cx, Data::params, |params| &params.density)
+          .height(BH)
+          .width(BW);
         create_button(
           cx,
           "sample",
@@ -65,6 +74,31 @@ pub fn right_col(cx: &mut Context) {
         .child_bottom(Stretch(1.0))
         .child_right(Stretch(1.0))
         .child_left(Stretch(1.0));
+      HStack::new(cx, |cx| {
+        create_button(
+          cx,
+          "auto gain",
+          Data::params,
+          BH,
+          BW,
+          |params| &params.auto_gain
+        );
+        create_button(
+          cx,
+          "soft clip",
+          Data::params,
+          BH,
+          BW,
+          |params| &params.soft_clip
+        );
+      })
+        .width(SW)
+        .height(Pixels(48.0))
+        .col_between(Percentage(10.0))
+        .child_top(Stretch(1.0))
+        .child_bottom(Stretch(1.0))
+        .child_right(Stretch(1.0))
+        .child_left(Stretch(1.0));
     })
       .width(Percentage(100.0));
 
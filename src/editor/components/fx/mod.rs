@@ -0,0 +1,50 @@
+use nih_plug_vizia::vizia::prelude::*;
+
+use crate::editor::Data;
+use super::slider::create_slider;
+use super::button::create_button;
+
+const LH: Units = Pixels(16.0);
+const LW: Units = Percentage(100.0);
+
+const SH: Units = Pixels(22.0);
+const SW: Units = Percentage(100.0);
+
+const BH: Units = Pixels(22.0);
+const BW: Units = Percentage(100.0);
+
+/// Effects applied to the grains.
+pub fn fx(cx: &mut Context) {
+  HStack::new(cx, |cx| {
+    VStack::new(cx, |cx| {
+      create_slider(cx, "filter",        Data::params, LH, LW, SH, SW, |params| &params.filter_mode);
+      create_slider(cx, "cutoff",        Data::params, LH, LW, SH, SW, |params| &params.filter_cutoff);
+      create_slider(cx, "resonance",     Data::params, LH, LW, SH, SW, |params| &params.filter_resonance);
+      create_slider(cx, "cutoff random", Data::params, LH, LW, SH, SW, |params| &params.filter_random);
+      create_button(cx, "per grain",     Data::params, BH, BW, |params| &params.filter_per_grain);
+    })
+      .row_between(Pixels(4.0));
+
+    VStack::new(cx, |cx| {
+      create_slider(cx, "reverb size", Data::params, LH, LW, SH, SW, |params| &params.reverb_size);
+      create_slider(cx, "damping",     Data::params, LH, LW, SH, SW, |params| &params.reverb_damping);
+      create_slider(cx, "reverb mix",  Data::params, LH, LW, SH, SW, |params| &params.reverb_mix);
+      create_slider(cx, "send",        Data::params, LH, LW, SH, SW, |params| &params.reverb_send);
+      create_slider(cx, "send random", Data::params, LH, LW, SH, SW, |params| &params.reverb_send_random);
+    })
+      .row_between(Pixels(4.0));
+
+    VStack::new(cx, |cx| {
+      create_slider(cx, "shimmer",  Data::params, LH, LW, SH, SW, |params| &params.shimmer_feedback);
+      create_slider(cx, "interval", Data::params, LH, LW, SH, SW, |params| &params.shimmer_interval);
+    })
+      .row_between(Pixels(4.0));
+  })
+    .width(Percentage(100.0))
+    .height(Percentage(80.0))
+    .left(Pixels(24.0))
+    .right(Pixels(24.0))
+    .top(Stretch(1.0))
+    .bottom(Pixels(16.0))
+    .col_between(Pixels(10.0));
+}
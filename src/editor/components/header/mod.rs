@@ -1,9 +1,55 @@
 use nih_plug_vizia::vizia::prelude::*;
 use nih_plug_vizia::assets;
 
+use crate::editor::{Data, EditorEvent, View};
+
 pub fn header(cx: &mut Context) {
+  HStack::new(cx, |cx| {
+    title(cx);
+    HStack::new(cx, |cx| {
+      VStack::new(cx, |cx| {
+        view_button(cx, "grains", View::Grains);
+        view_button(cx, "trigger", View::Trigger);
+        view_button(cx, "steps", View::Steps);
+        view_button(cx, "pitch", View::Pitch);
+        view_button(cx, "lfo", View::Modulation);
+      })
+        .row_between(Pixels(2.0));
+      VStack::new(cx, |cx| {
+        view_button(cx, "random", View::Random);
+        view_button(cx, "shape", View::Shape);
+        view_button(cx, "routing", View::Routing);
+        view_button(cx, "fx", View::Fx);
+      })
+        .row_between(Pixels(2.0));
+    })
+      .width(Pixels(148.0))
+      .height(Auto)
+      .top(Stretch(1.0))
+      .bottom(Stretch(1.0))
+      .right(Pixels(42.0))
+      .col_between(Pixels(4.0));
+  })
+    .height(Percentage(20.0));
+}
+
+fn view_button(cx: &mut Context, name: &'static str, view: View) {
+  Button::new(
+    cx,
+    move |cx| cx.emit(EditorEvent::SetView(view)),
+    move |cx| Label::new(cx, name)
+  )
+    .class("view")
+    .checked(Data::view.map(move |v| *v == view))
+    .width(Stretch(1.0))
+    .height(Pixels(16.0))
+    .child_left(Stretch(1.0))
+    .child_right(Stretch(1.0));
+}
+
+fn title(cx: &mut Context) {
   Label::new(cx, "havregryn")
-    .height(Percentage(20.0))
+    .height(Percentage(100.0))
     .font_family(vec![FamilyOwned::Name(String::from(assets::NOTO_SANS))])
     .font_weight(FontWeightKeyword::Thin)
     .font_size(35.0)
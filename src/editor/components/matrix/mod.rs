@@ -0,0 +1,53 @@
+use nih_plug_vizia::widgets::ParamSlider;
+use nih_plug_vizia::vizia::prelude::*;
+
+use crate::editor::Data;
+use crate::modmatrix::SLOTS;
+
+const LH: Units = Pixels(20.0);
+
+const RH: Units = Pixels(24.0);
+
+pub fn matrix(cx: &mut Context) {
+  VStack::new(cx, |cx| {
+    routing(cx);
+  })
+    .width(Percentage(100.0))
+    .height(Percentage(80.0))
+    .left(Pixels(42.0))
+    .right(Pixels(42.0))
+    .top(Stretch(1.0))
+    .bottom(Pixels(16.0))
+    .row_between(Pixels(12.0));
+}
+
+/// One row per slot in the modulation matrix: source, destination and depth.
+fn routing(cx: &mut Context) {
+  VStack::new(cx, |cx| {
+    HStack::new(cx, |cx| {
+      Label::new(cx, "source").width(Stretch(1.0));
+      Label::new(cx, "destination").width(Stretch(1.0));
+      Label::new(cx, "depth").width(Stretch(1.0));
+    })
+      .height(LH)
+      .col_between(Pixels(12.0));
+
+    for i in 0..SLOTS {
+      HStack::new(cx, |cx| {
+        ParamSlider::new(cx, Data::params, move |params| &params.mod_slots[i].source)
+          .height(RH)
+          .width(Stretch(1.0));
+        ParamSlider::new(cx, Data::params, move |params| &params.mod_slots[i].dest)
+          .height(RH)
+          .width(Stretch(1.0));
+        ParamSlider::new(cx, Data::params, move |params| &params.mod_slots[i].depth)
+          .height(RH)
+          .width(Stretch(1.0));
+      })
+        .height(RH)
+        .col_between(Pixels(12.0));
+    }
+  })
+    .height(Auto)
+    .row_between(Pixels(4.0));
+}
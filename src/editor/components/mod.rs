@@ -4,3 +4,11 @@ pub mod slider;
 pub mod header;
 pub mod body;
 
+pub mod matrix;
+pub mod modulation;
+pub mod shape;
+pub mod fx;
+pub mod trigger;
+pub mod steps;
+pub mod random;
+pub mod pitch;
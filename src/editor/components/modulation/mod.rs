@@ -0,0 +1,84 @@
+use nih_plug_vizia::vizia::prelude::*;
+
+use crate::editor::Data;
+use super::slider::create_slider;
+use super::button::create_button;
+
+const LH: Units = Pixels(16.0);
+const LW: Units = Percentage(100.0);
+
+const SH: Units = Pixels(22.0);
+const SW: Units = Percentage(100.0);
+
+const BH: Units = Pixels(22.0);
+const BW: Units = Percentage(46.0);
+
+/// Frequency, shape and sync settings of the rate modulator and the matrix LFOs,
+/// and the settings of the envelope follower.
+pub fn modulation(cx: &mut Context) {
+  HStack::new(cx, |cx| {
+    VStack::new(cx, |cx| {
+      create_slider(cx, "mod freq",  Data::params, LH, LW, SH, SW, |params| &params.rate_mod_freq);
+      create_slider(cx, "shape",     Data::params, LH, LW, SH, SW, |params| &params.rate_mod_shape);
+      create_slider(cx, "morph",     Data::params, LH, LW, SH, SW, |params| &params.rate_mod_morph);
+      create_slider(cx, "division",  Data::params, LH, LW, SH, SW, |params| &params.rate_mod_division);
+      create_slider(cx, "feel",      Data::params, LH, LW, SH, SW, |params| &params.rate_mod_feel);
+      create_slider(cx, "phase",     Data::params, LH, LW, SH, SW, |params| &params.rate_mod_phase);
+      HStack::new(cx, |cx| {
+        create_button(cx, "sync",  Data::params, BH, BW, |params| &params.rate_mod_sync);
+        create_button(cx, "retrig", Data::params, BH, BW, |params| &params.rate_mod_retrigger);
+      })
+        .height(Auto)
+        .col_between(Stretch(1.0));
+    })
+      .row_between(Pixels(4.0));
+
+    VStack::new(cx, |cx| {
+      create_slider(cx, "lfo 1 freq", Data::params, LH, LW, SH, SW, |params| &params.lfo1.freq);
+      create_slider(cx, "shape",      Data::params, LH, LW, SH, SW, |params| &params.lfo1.shape);
+      create_slider(cx, "morph",      Data::params, LH, LW, SH, SW, |params| &params.lfo1.morph);
+      create_slider(cx, "division",   Data::params, LH, LW, SH, SW, |params| &params.lfo1.division);
+      create_slider(cx, "feel",       Data::params, LH, LW, SH, SW, |params| &params.lfo1.feel);
+      create_slider(cx, "phase",      Data::params, LH, LW, SH, SW, |params| &params.lfo1.phase);
+      HStack::new(cx, |cx| {
+        create_button(cx, "sync",   Data::params, BH, BW, |params| &params.lfo1.sync);
+        create_button(cx, "retrig", Data::params, BH, BW, |params| &params.lfo1.retrigger);
+      })
+        .height(Auto)
+        .col_between(Stretch(1.0));
+    })
+      .row_between(Pixels(4.0));
+
+    VStack::new(cx, |cx| {
+      create_slider(cx, "lfo 2 freq", Data::params, LH, LW, SH, SW, |params| &params.lfo2.freq);
+      create_slider(cx, "shape",      Data::params, LH, LW, SH, SW, |params| &params.lfo2.shape);
+      create_slider(cx, "morph",      Data::params, LH, LW, SH, SW, |params| &params.lfo2.morph);
+      create_slider(cx, "division",   Data::params, LH, LW, SH, SW, |params| &params.lfo2.division);
+      create_slider(cx, "feel",       Data::params, LH, LW, SH, SW, |params| &params.lfo2.feel);
+      create_slider(cx, "phase",      Data::params, LH, LW, SH, SW, |params| &params.lfo2.phase);
+      HStack::new(cx, |cx| {
+        create_button(cx, "sync",   Data::params, BH, BW, |params| &params.lfo2.sync);
+        create_button(cx, "retrig", Data::params, BH, BW, |params| &params.lfo2.retrigger);
+      })
+        .height(Auto)
+        .col_between(Stretch(1.0));
+    })
+      .row_between(Pixels(4.0));
+
+    VStack::new(cx, |cx| {
+      create_slider(cx, "random freq", Data::params, LH, LW, SH, SW, |params| &params.random_freq);
+      create_button(cx, "reset on play", Data::params, BH, Percentage(100.0), |params| &params.lfo_reset_on_play);
+      create_slider(cx, "env attack",  Data::params, LH, LW, SH, SW, |params| &params.env_attack);
+      create_slider(cx, "env release", Data::params, LH, LW, SH, SW, |params| &params.env_release);
+      create_slider(cx, "env input",   Data::params, LH, LW, SH, SW, |params| &params.env_input);
+    })
+      .row_between(Pixels(4.0));
+  })
+    .width(Percentage(100.0))
+    .height(Percentage(80.0))
+    .left(Pixels(24.0))
+    .right(Pixels(24.0))
+    .top(Stretch(1.0))
+    .bottom(Pixels(16.0))
+    .col_between(Pixels(10.0));
+}
@@ -0,0 +1,100 @@
+use nih_plug_vizia::widgets::{ParamButton, ParamButtonExt};
+use nih_plug_vizia::vizia::prelude::*;
+
+use crate::editor::{Data, EditorEvent};
+use super::slider::create_slider;
+use super::button::create_button;
+
+const LH: Units = Pixels(16.0);
+const LW: Units = Percentage(100.0);
+
+const SH: Units = Pixels(22.0);
+const SW: Units = Stretch(1.0);
+
+const BH: Units = Pixels(22.0);
+
+/// Scale quantizer of the grain pitch, and the tuning of the MIDI keys.
+pub fn pitch(cx: &mut Context) {
+  VStack::new(cx, |cx| {
+    HStack::new(cx, |cx| {
+      create_slider(cx, "scale",       Data::params, LH, LW, SH, SW, |params| &params.scale);
+      create_slider(cx, "root",        Data::params, LH, LW, SH, SW, |params| &params.scale_root);
+      create_slider(cx, "octave jump", Data::params, LH, LW, SH, SW, |params| &params.octave_jump);
+    })
+      .height(Auto)
+      .col_between(Pixels(10.0));
+
+    VStack::new(cx, |cx| {
+      Label::new(cx, "user scale, semitones from the root")
+        .height(LH)
+        .width(LW);
+      HStack::new(cx, |cx| {
+        for i in 0..12 {
+          ParamButton::new(cx, Data::params, move |params| &params.user_scale[i].on)
+            .with_label(format!("{}", i))
+            .class("step")
+            .width(Stretch(1.0))
+            .height(BH)
+            .child_left(Stretch(1.0))
+            .child_right(Stretch(1.0));
+        }
+      })
+        .height(Auto)
+        .col_between(Pixels(2.0));
+    })
+      .height(Auto);
+
+    HStack::new(cx, |cx| {
+      create_slider(cx, "A4",       Data::params, LH, LW, SH, SW, |params| &params.a4);
+      create_slider(cx, "root key", Data::params, LH, LW, SH, SW, |params| &params.root_key);
+      VStack::new(cx, |cx| {
+        // detected pitch of the last capture
+        Label::new(cx, Data::detected)
+          .height(LH)
+          .width(LW);
+        create_button(cx, "auto root", Data::params, BH, SW, |params| &params.auto_root);
+      })
+        .height(Auto);
+    })
+      .height(Auto)
+      .col_between(Pixels(10.0));
+
+    VStack::new(cx, |cx| {
+      Label::new(cx, "scala .scl or .kbm file")
+        .height(LH)
+        .width(LW);
+      HStack::new(cx, |cx| {
+        Textbox::new(cx, Data::tuning_path)
+          .on_edit(|cx, path| cx.emit(EditorEvent::SetTuningPath(path)))
+          .width(Stretch(4.0))
+          .height(BH);
+        Button::new(cx, |cx| cx.emit(EditorEvent::LoadTuning), |cx| Label::new(cx, "load"))
+          .class("view")
+          .width(Stretch(1.0))
+          .height(BH)
+          .child_left(Stretch(1.0))
+          .child_right(Stretch(1.0));
+        Button::new(cx, |cx| cx.emit(EditorEvent::ClearTuning), |cx| Label::new(cx, "12-TET"))
+          .class("view")
+          .width(Stretch(1.0))
+          .height(BH)
+          .child_left(Stretch(1.0))
+          .child_right(Stretch(1.0));
+      })
+        .height(Auto)
+        .col_between(Pixels(4.0));
+      // the loaded scale, or why the last file failed to load
+      Label::new(cx, Data::tuning_status)
+        .height(LH)
+        .width(LW);
+    })
+      .height(Auto);
+  })
+    .width(Percentage(100.0))
+    .height(Percentage(80.0))
+    .left(Pixels(24.0))
+    .right(Pixels(24.0))
+    .top(Stretch(1.0))
+    .bottom(Pixels(16.0))
+    .row_between(Pixels(8.0));
+}
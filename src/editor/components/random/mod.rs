@@ -0,0 +1,48 @@
+use nih_plug_vizia::vizia::prelude::*;
+
+use crate::editor::Data;
+use super::slider::create_slider;
+use super::button::create_button;
+
+const LH: Units = Pixels(16.0);
+const LW: Units = Percentage(100.0);
+
+const SH: Units = Pixels(22.0);
+const SW: Units = Percentage(100.0);
+
+const BH: Units = Pixels(22.0);
+const BW: Units = Percentage(100.0);
+
+/// Envelope shape, and the random ranges every grain draws its own values from.
+pub fn random(cx: &mut Context) {
+  HStack::new(cx, |cx| {
+    VStack::new(cx, |cx| {
+      create_slider(cx, "env shape",       Data::params, LH, LW, SH, SW, |params| &params.env_shape);
+      create_slider(cx, "shape random",    Data::params, LH, LW, SH, SW, |params| &params.env_shape_random);
+      create_slider(cx, "duration random", Data::params, LH, LW, SH, SW, |params| &params.duration_random);
+      create_slider(cx, "amp random",      Data::params, LH, LW, SH, SW, |params| &params.amp_random);
+      create_slider(cx, "seed",            Data::params, LH, LW, SH, SW, |params| &params.seed);
+      create_button(cx, "reseed on play",  Data::params, BH, BW, |params| &params.reseed_on_play);
+    })
+      .row_between(Pixels(4.0));
+
+    VStack::new(cx, |cx| {
+      create_slider(cx, "semitone random", Data::params, LH, LW, SH, SW, |params| &params.rate_random_semitones);
+      create_slider(cx, "cent random",     Data::params, LH, LW, SH, SW, |params| &params.rate_random_cents);
+      create_slider(cx, "jitter",          Data::params, LH, LW, SH, SW, |params| &params.jitter);
+      create_slider(cx, "jitter mode",     Data::params, LH, LW, SH, SW, |params| &params.jitter_mode);
+      create_slider(cx, "jitter edge",     Data::params, LH, LW, SH, SW, |params| &params.jitter_edge);
+      create_slider(cx, "stereo spread",   Data::params, LH, LW, SH, SW, |params| &params.spread);
+      create_slider(cx, "pan mode",        Data::params, LH, LW, SH, SW, |params| &params.pan_mode);
+      create_slider(cx, "width",           Data::params, LH, LW, SH, SW, |params| &params.width);
+    })
+      .row_between(Pixels(4.0));
+  })
+    .width(Percentage(100.0))
+    .height(Percentage(80.0))
+    .left(Pixels(24.0))
+    .right(Pixels(24.0))
+    .top(Stretch(1.0))
+    .bottom(Pixels(16.0))
+    .col_between(Pixels(10.0));
+}
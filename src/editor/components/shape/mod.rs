@@ -0,0 +1,149 @@
+use std::sync::Arc;
+
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::vizia::vg;
+
+use crate::HavregrynParams;
+use crate::custom::Point;
+use crate::editor::Data;
+
+/// Radius, in pixels, in which a click grabs an existing breakpoint.
+const GRAB: f32 = 8.0;
+
+pub fn shape(cx: &mut Context) {
+  VStack::new(cx, |cx| {
+    Label::new(cx, "custom lfo shape")
+      .height(Pixels(20.0));
+    ShapeEditor::new(cx, Data::params)
+      .width(Percentage(100.0))
+      .height(Stretch(1.0));
+    Label::new(cx, "click to add, drag to move, right click to remove")
+      .height(Pixels(20.0));
+  })
+    .width(Percentage(100.0))
+    .height(Percentage(80.0))
+    .left(Pixels(42.0))
+    .right(Pixels(42.0))
+    .top(Stretch(1.0))
+    .bottom(Pixels(16.0))
+    .row_between(Pixels(6.0));
+}
+
+/// Draws the breakpoints of the custom LFO shape and lets the user edit them.
+pub struct ShapeEditor {
+  params: Arc<HavregrynParams>,
+  dragging: Option<usize>,
+}
+
+impl ShapeEditor {
+  pub fn new<L>(cx: &mut Context, params: L) -> Handle<Self>
+  where
+    L: Lens<Target = Arc<HavregrynParams>>
+  {
+    Self {
+      params: params.get(cx),
+      dragging: None,
+    }
+      .build(cx, |_| {})
+  }
+
+  /// Converts a position in the view to a breakpoint.
+  fn to_point(bounds: BoundingBox, x: f32, y: f32) -> Point {
+    Point {
+      x: (x - bounds.x) / bounds.w,
+      y: 1.0 - 2.0 * (y - bounds.y) / bounds.h,
+    }
+  }
+
+  /// Converts a breakpoint to a position in the view.
+  fn to_view(bounds: BoundingBox, point: &Point) -> (f32, f32) {
+    (
+      bounds.x + point.x * bounds.w,
+      bounds.y + (1.0 - point.y) * 0.5 * bounds.h,
+    )
+  }
+
+  /// Index of the breakpoint under the cursor.
+  fn grab(&self, bounds: BoundingBox, x: f32, y: f32) -> Option<usize> {
+    self.params.custom_shape.points()
+      .iter()
+      .position(|p| {
+        let (px, py) = Self::to_view(bounds, p);
+        (px - x).hypot(py - y) <= GRAB
+      })
+  }
+}
+
+impl View for ShapeEditor {
+  fn element(&self) -> Option<&'static str> {
+    Some("shape-editor")
+  }
+
+  fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+    event.map(|window_event, _| match window_event {
+      WindowEvent::MouseDown(MouseButton::Left) => {
+        let bounds = cx.bounds();
+        let (x, y) = (cx.mouse().cursorx, cx.mouse().cursory);
+        self.dragging = self.grab(bounds, x, y)
+          .or_else(|| self.params.custom_shape.insert(Self::to_point(bounds, x, y)));
+        if self.dragging.is_some() {
+          cx.capture();
+          cx.needs_redraw();
+        }
+      },
+      WindowEvent::MouseDown(MouseButton::Right) => {
+        let bounds = cx.bounds();
+        if let Some(index) = self.grab(bounds, cx.mouse().cursorx, cx.mouse().cursory) {
+          self.params.custom_shape.remove(index);
+          cx.needs_redraw();
+        }
+      },
+      WindowEvent::MouseMove(x, y) => {
+        if let Some(index) = self.dragging {
+          let point = Self::to_point(cx.bounds(), *x, *y);
+          self.dragging = Some(self.params.custom_shape.move_point(index, point));
+          cx.needs_redraw();
+        }
+      },
+      WindowEvent::MouseUp(MouseButton::Left) => {
+        if self.dragging.take().is_some() {
+          cx.release();
+        }
+      },
+      _ => {}
+    });
+  }
+
+  fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+    let bounds = cx.bounds();
+    if bounds.w <= 0.0 || bounds.h <= 0.0 { return }
+    let points = self.params.custom_shape.points();
+
+    // zero line
+    let mut axis = vg::Path::new();
+    axis.move_to(bounds.x, bounds.y + bounds.h * 0.5);
+    axis.line_to(bounds.x + bounds.w, bounds.y + bounds.h * 0.5);
+    canvas.stroke_path(&mut axis, &vg::Paint::color(vg::Color::rgb(0x40, 0x40, 0x40)).with_line_width(1.0));
+
+    // one cycle of the shape, wrapping around the edges like the rendered table does
+    let (first, last) = (points[0], points[points.len() - 1]);
+    let edge = last.y + (first.y - last.y) * (1.0 - last.x) / (first.x + 1.0 - last.x);
+    let mut line = vg::Path::new();
+    let (x, y) = Self::to_view(bounds, &Point { x: 0.0, y: edge });
+    line.move_to(x, y);
+    points.iter().for_each(|p| {
+      let (x, y) = Self::to_view(bounds, p);
+      line.line_to(x, y);
+    });
+    let (x, y) = Self::to_view(bounds, &Point { x: 1.0, y: edge });
+    line.line_to(x, y);
+    canvas.stroke_path(&mut line, &vg::Paint::color(vg::Color::rgb(0xfa, 0xfa, 0xfa)).with_line_width(1.5));
+
+    let mut handles = vg::Path::new();
+    points.iter().for_each(|p| {
+      let (x, y) = Self::to_view(bounds, p);
+      handles.circle(x, y, 3.0);
+    });
+    canvas.fill_path(&mut handles, &vg::Paint::color(vg::Color::rgb(0xff, 0x25, 0x5c)));
+  }
+}
@@ -0,0 +1,108 @@
+use nih_plug_vizia::widgets::{ParamButton, ParamButtonExt};
+use nih_plug_vizia::vizia::prelude::*;
+
+use crate::editor::{Data, EditorEvent};
+use crate::sequencer::STEPS;
+use super::slider::create_slider;
+use super::button::create_button;
+
+const LH: Units = Pixels(16.0);
+const LW: Units = Percentage(100.0);
+
+const SH: Units = Pixels(22.0);
+const SW: Units = Stretch(1.0);
+
+const BH: Units = Pixels(22.0);
+
+/// Steps in each row of on switches.
+const ROW: usize = 16;
+
+/// The step sequencer, its settings, the on switch of every step
+/// and the offsets of the step selected in the step editor.
+pub fn steps(cx: &mut Context) {
+  VStack::new(cx, |cx| {
+    HStack::new(cx, |cx| {
+      create_slider(cx, "length",   Data::params, LH, LW, SH, SW, |params| &params.seq_length);
+      create_slider(cx, "division", Data::params, LH, LW, SH, SW, |params| &params.seq_division);
+      create_slider(cx, "pulses",   Data::params, LH, LW, SH, SW, |params| &params.seq_pulses);
+      create_slider(cx, "rotate",   Data::params, LH, LW, SH, SW, |params| &params.seq_rotate);
+    })
+      .height(Auto)
+      .col_between(Pixels(10.0));
+
+    HStack::new(cx, |cx| {
+      create_button(cx, "sequencer", Data::params, BH, Stretch(1.0), |params| &params.seq_on);
+      create_button(cx, "euclid",    Data::params, BH, Stretch(1.0), |params| &params.seq_euclid);
+    })
+      .height(Auto)
+      .col_between(Pixels(10.0));
+
+    for row in 0..STEPS / ROW {
+      HStack::new(cx, |cx| {
+        for i in row * ROW..(row + 1) * ROW {
+          ParamButton::new(cx, Data::params, move |params| &params.steps[i].on)
+            .with_label(format!("{}", i + 1))
+            .class("step")
+            .width(Stretch(1.0))
+            .height(BH)
+            .child_left(Stretch(1.0))
+            .child_right(Stretch(1.0));
+        }
+      })
+        .height(Auto)
+        .col_between(Pixels(2.0));
+    }
+
+    step_editor(cx);
+  })
+    .width(Percentage(100.0))
+    .height(Percentage(80.0))
+    .left(Pixels(24.0))
+    .right(Pixels(24.0))
+    .top(Stretch(1.0))
+    .bottom(Pixels(16.0))
+    .row_between(Pixels(8.0));
+}
+
+/// Probability and offsets of a single step.
+fn step_editor(cx: &mut Context) {
+  HStack::new(cx, |cx| {
+    VStack::new(cx, |cx| {
+      Label::new(cx, Data::step.map(|step| format!("step {}", step + 1)))
+        .height(LH)
+        .width(LW);
+      HStack::new(cx, |cx| {
+        Button::new(cx, |cx| cx.emit(EditorEvent::MoveStep(-1)), |cx| Label::new(cx, "<"))
+          .class("view")
+          .width(Stretch(1.0))
+          .height(SH)
+          .child_left(Stretch(1.0))
+          .child_right(Stretch(1.0));
+        Button::new(cx, |cx| cx.emit(EditorEvent::MoveStep(1)), |cx| Label::new(cx, ">"))
+          .class("view")
+          .width(Stretch(1.0))
+          .height(SH)
+          .child_left(Stretch(1.0))
+          .child_right(Stretch(1.0));
+      })
+        .height(Auto)
+        .col_between(Pixels(2.0));
+    })
+      .width(Pixels(60.0))
+      .height(Auto);
+
+    Binding::new(cx, Data::step, |cx, step| {
+      let i = step.get(cx);
+      HStack::new(cx, |cx| {
+        create_slider(cx, "probability", Data::params, LH, LW, SH, SW, move |params| &params.steps[i].probability);
+        create_slider(cx, "position",    Data::params, LH, LW, SH, SW, move |params| &params.steps[i].position);
+        create_slider(cx, "rate",        Data::params, LH, LW, SH, SW, move |params| &params.steps[i].rate);
+        create_slider(cx, "amp",         Data::params, LH, LW, SH, SW, move |params| &params.steps[i].amp);
+      })
+        .height(Auto)
+        .col_between(Pixels(10.0));
+    });
+  })
+    .height(Auto)
+    .col_between(Pixels(10.0));
+}
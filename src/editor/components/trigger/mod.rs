@@ -0,0 +1,51 @@
+use nih_plug_vizia::vizia::prelude::*;
+
+use crate::editor::Data;
+use super::slider::create_slider;
+use super::button::create_button;
+
+const LH: Units = Pixels(16.0);
+const LW: Units = Percentage(100.0);
+
+const SH: Units = Pixels(22.0);
+const SW: Units = Percentage(100.0);
+
+const BH: Units = Pixels(22.0);
+const BW: Units = Percentage(100.0);
+
+/// When new grains are triggered.
+pub fn trigger(cx: &mut Context) {
+  HStack::new(cx, |cx| {
+    VStack::new(cx, |cx| {
+      create_slider(cx, "trigger",   Data::params, LH, LW, SH, SW, |params| &params.trigger);
+      create_slider(cx, "density",   Data::params, LH, LW, SH, SW, |params| &params.density);
+      create_slider(cx, "deviation", Data::params, LH, LW, SH, SW, |params| &params.deviation);
+      create_slider(cx, "division",  Data::params, LH, LW, SH, SW, |params| &params.trigger_division);
+      create_slider(cx, "feel",      Data::params, LH, LW, SH, SW, |params| &params.trigger_feel);
+      create_slider(cx, "swing",     Data::params, LH, LW, SH, SW, |params| &params.trigger_swing);
+      create_button(cx, "sync",      Data::params, BH, BW, |params| &params.trigger_sync);
+    })
+      .row_between(Pixels(4.0));
+
+    VStack::new(cx, |cx| {
+      create_slider(cx, "probability", Data::params, LH, LW, SH, SW, |params| &params.trigger_probability);
+      create_slider(cx, "ratchet",     Data::params, LH, LW, SH, SW, |params| &params.ratchet);
+    })
+      .row_between(Pixels(4.0));
+
+    VStack::new(cx, |cx| {
+      create_slider(cx, "threshold",   Data::params, LH, LW, SH, SW, |params| &params.transient_threshold);
+      create_slider(cx, "sensitivity", Data::params, LH, LW, SH, SW, |params| &params.transient_sensitivity);
+      create_slider(cx, "hold",        Data::params, LH, LW, SH, SW, |params| &params.transient_hold);
+      create_button(cx, "sidechain",   Data::params, BH, BW, |params| &params.transient_on);
+    })
+      .row_between(Pixels(4.0));
+  })
+    .width(Percentage(100.0))
+    .height(Percentage(80.0))
+    .left(Pixels(24.0))
+    .right(Pixels(24.0))
+    .top(Stretch(1.0))
+    .bottom(Pixels(16.0))
+    .col_between(Pixels(10.0));
+}
@@ -1,26 +1,102 @@
 mod components;
 
 use std::sync::Arc;
+use std::time::Duration;
 
 use nih_plug::editor::Editor;
 use nih_plug_vizia::{assets, create_vizia_editor, ViziaState, ViziaTheming};
 use nih_plug_vizia::vizia::prelude::*;
 
-use crate::HavregrynParams;
+use crate::{HavregrynParams, Task};
+use crate::detect::Detector;
+use crate::sequencer::STEPS;
 use components::{
   header::header,
   body::body,
+  matrix::matrix,
+  modulation::modulation,
+  shape::shape,
+  fx::fx,
+  trigger::trigger,
+  steps::steps,
+  random::random,
+  pitch::pitch,
 };
 
 #[derive(Lens, Clone)]
 pub(crate) struct Data {
     pub params: Arc<HavregrynParams>,
+    pub view: View,
+    /// Step shown in the step editor.
+    pub step: usize,
+    /// Path of the Scala file to load.
+    pub tuning_path: String,
+    /// Runs a task on the background thread.
+    #[lens(ignore)]
+    pub tasks: Arc<dyn Fn(Task) + Send + Sync>,
+    /// Pitch of the captured buffer.
+    pub detector: Arc<Detector>,
+    /// The detected pitch as shown, polled from `detector`.
+    pub detected: String,
+    /// The loaded scale or why it failed to load, polled from the tuning.
+    pub tuning_status: String,
 }
 
-impl Model for Data {}
+/// The page shown below the header.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum View {
+  Grains,
+  Trigger,
+  Steps,
+  Pitch,
+  Random,
+  Modulation,
+  Shape,
+  Routing,
+  Fx,
+}
+
+// `Data` the trait, not the model above.
+impl nih_plug_vizia::vizia::prelude::Data for View {
+  fn same(&self, other: &Self) -> bool { self == other }
+}
+
+pub(crate) enum EditorEvent {
+  SetView(View),
+  /// Moves the step editor forward or backward, wrapping around.
+  MoveStep(isize),
+  SetTuningPath(String),
+  LoadTuning,
+  ClearTuning,
+  /// Reads the detected pitch and the tuning status, sent by a timer.
+  Poll,
+}
+
+impl Model for Data {
+  fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+    event.map(|e, _| match e {
+      EditorEvent::SetView(view) => { self.view = *view; },
+      EditorEvent::MoveStep(by)  => {
+        self.step = (self.step as isize + by).rem_euclid(STEPS as isize) as usize;
+      },
+      EditorEvent::SetTuningPath(path) => { self.tuning_path = path.clone(); },
+      EditorEvent::LoadTuning => {
+        let path = self.tuning_path.trim();
+        if !path.is_empty() { (self.tasks)(Task::LoadTuning(path.into())); }
+      },
+      EditorEvent::ClearTuning => { (self.tasks)(Task::ClearTuning); },
+      EditorEvent::Poll => {
+        let detected = self.detector.describe(self.params.a4.value());
+        if detected != self.detected { self.detected = detected; }
+        let status = self.params.tuning.status();
+        if status != self.tuning_status { self.tuning_status = status; }
+      },
+    });
+  }
+}
 
 pub(crate) fn default_state() -> Arc<ViziaState> {
-  ViziaState::new(||(500, 440))
+  ViziaState::new(||(500, 480))
 }
 
 pub fn create(params: Data, editor_state: Arc<ViziaState>) -> Option<Box<dyn Editor>> {
@@ -29,6 +105,11 @@ pub fn create(params: Data, editor_state: Arc<ViziaState>) -> Option<Box<dyn Edi
     assets::register_noto_sans_thin(cx);
     assets::register_noto_sans_light(cx);
     params.clone().build(cx);
+    // the detector and the tuning files run on the background thread, so their results are polled
+    let poll = cx.add_timer(Duration::from_millis(200), None, |cx, action| {
+      if let TimerAction::Tick(_) = action { cx.emit(EditorEvent::Poll); }
+    });
+    cx.start_timer(poll);
     build_gui(cx);
   })
 }
@@ -36,7 +117,19 @@ pub fn create(params: Data, editor_state: Arc<ViziaState>) -> Option<Box<dyn Edi
 fn build_gui(cx: &mut Context) {
   VStack::new(cx, |cx| {
     header(cx);
-    body(cx);
+    Binding::new(cx, Data::view, |cx, view| {
+      match view.get(cx) {
+        View::Grains     => { body(cx); },
+        View::Trigger    => { trigger(cx); },
+        View::Steps      => { steps(cx); },
+        View::Random     => { random(cx); },
+        View::Pitch      => { pitch(cx); },
+        View::Modulation => { modulation(cx); },
+        View::Shape      => { shape(cx); },
+        View::Routing    => { matrix(cx); },
+        View::Fx         => { fx(cx); },
+      }
+    });
   });
 }
 
@@ -0,0 +1,124 @@
+use std::f32::consts::PI;
+use nih_plug::prelude::*;
+
+#[derive(Enum, PartialEq, Clone, Copy)]
+pub enum FilterMode {
+  Off,
+  #[name = "LP"]
+  LowPass,
+  #[name = "HP"]
+  HighPass,
+  #[name = "BP"]
+  BandPass,
+  Notch,
+}
+
+/// Coefficients for `Svf`, cheap to copy so that every grain can carry its own.
+#[derive(Clone, Copy)]
+pub struct Coeffs {
+  mode: FilterMode,
+  k: f32,
+  a1: f32,
+  a2: f32,
+  a3: f32,
+}
+
+impl Coeffs {
+  /// Passes the signal through untouched.
+  pub const OFF: Self = Self { mode: FilterMode::Off, k: 0.0, a1: 0.0, a2: 0.0, a3: 0.0 };
+
+  /// `resonance` in the range `(0.0, 1.0)`, maps to a Q from `0.5` to `20.0`.
+  #[inline]
+  pub fn new(mode: FilterMode, cutoff: f32, resonance: f32, samplerate: f32) -> Self {
+    if mode == FilterMode::Off || samplerate <= 0.0 { return Self::OFF }
+    let cutoff = cutoff.clamp(10.0, samplerate * 0.49);
+    let g = (PI * cutoff / samplerate).tan();
+    let k = 1.0 / (0.5 + resonance.clamp(0.0, 1.0) * 19.5);
+    let a1 = 1.0 / (1.0 + g * (g + k));
+    let a2 = g * a1;
+    let a3 = g * a2;
+    Self { mode, k, a1, a2, a3 }
+  }
+}
+
+/// Topology preserving state variable filter.
+#[derive(Clone, Copy, Default)]
+pub struct Svf {
+  ic1eq: f32,
+  ic2eq: f32,
+}
+
+impl Svf {
+  pub const fn new() -> Self {
+    Self { ic1eq: 0.0, ic2eq: 0.0 }
+  }
+
+  #[inline]
+  pub fn process(&mut self, input: f32, c: &Coeffs) -> f32 {
+    if c.mode == FilterMode::Off { return input }
+    let v3 = input - self.ic2eq;
+    let v1 = c.a1 * self.ic1eq + c.a2 * v3;
+    let v2 = self.ic2eq + c.a2 * self.ic1eq + c.a3 * v3;
+    self.ic1eq = 2.0 * v1 - self.ic1eq;
+    self.ic2eq = 2.0 * v2 - self.ic2eq;
+    match c.mode {
+      FilterMode::Off      => { input },
+      FilterMode::LowPass  => { v2 },
+      FilterMode::HighPass => { input - c.k * v1 - v2 },
+      // scaled to unity gain at the cutoff
+      FilterMode::BandPass => { c.k * v1 },
+      FilterMode::Notch    => { input - c.k * v1 },
+    }
+  }
+
+  #[inline]
+  pub fn reset(&mut self) {
+    self.ic1eq = 0.0;
+    self.ic2eq = 0.0;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SR: f32 = 48000.0;
+
+  fn settle(mode: FilterMode, input: impl Fn(usize) -> f32) -> f32 {
+    let c = Coeffs::new(mode, 1000.0, 0.0, SR);
+    let mut svf = Svf::default();
+    let mut peak = 0.0f32;
+    for n in 0..48000 {
+      let out = svf.process(input(n), &c);
+      // measure the last tenth of a second
+      if n > 43200 { peak = peak.max(out.abs()); }
+    }
+    peak
+  }
+
+  #[test]
+  fn lowpass_passes_dc() {
+    assert!((settle(FilterMode::LowPass, |_| 1.0) - 1.0).abs() < 1e-3);
+  }
+
+  #[test]
+  fn highpass_blocks_dc() {
+    assert!(settle(FilterMode::HighPass, |_| 1.0) < 1e-3);
+  }
+
+  #[test]
+  fn notch_removes_cutoff() {
+    let sine = |n: usize| (2.0 * PI * 1000.0 * n as f32 / SR).sin();
+    assert!(settle(FilterMode::Notch, sine) < 0.05);
+    assert!(settle(FilterMode::BandPass, sine) > 0.9);
+  }
+
+  #[test]
+  fn off_is_identity() {
+    let mut svf = Svf::default();
+    for n in 0..64 {
+      let x = n as f32 * 0.1 - 3.0;
+      assert_eq!(svf.process(x, &Coeffs::OFF), x);
+    }
+  }
+}
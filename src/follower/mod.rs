@@ -0,0 +1,91 @@
+/// Peak envelope follower with separate attack and release times.
+pub struct Follower {
+  envelope: f32,
+  attack: f32,
+  release: f32,
+  attack_coeff: f32,
+  release_coeff: f32,
+  samplerate: f32,
+}
+
+impl Follower {
+  pub fn new(attack: f32, release: f32) -> Self {
+    Self {
+      envelope: 0.0,
+      attack,
+      release,
+      attack_coeff: 0.0,
+      release_coeff: 0.0,
+      samplerate: 0.0,
+    }
+  }
+
+  /// One-pole coefficient reaching ~63% of a step after `time` seconds.
+  #[inline]
+  fn coeff(&self, time: f32) -> f32 {
+    if time <= 0.0 || self.samplerate <= 0.0 { return 0.0 }
+    (-1.0 / (time * self.samplerate)).exp()
+  }
+
+  /// Returns the current envelope, in the range `(0.0, 1.0)` for input within `(-1.0, 1.0)`.
+  #[inline]
+  pub fn play(&mut self, input: f32) -> f32 {
+    let rect = input.abs();
+    let coeff = if rect > self.envelope { self.attack_coeff } else { self.release_coeff };
+    self.envelope = rect + coeff * (self.envelope - rect);
+    self.envelope
+  }
+
+  /// Attack and release times in seconds, coefficients are only recalculated on change.
+  #[inline]
+  pub fn set_times(&mut self, attack: f32, release: f32) {
+    if attack != self.attack {
+      self.attack = attack;
+      self.attack_coeff = self.coeff(attack);
+    }
+    if release != self.release {
+      self.release = release;
+      self.release_coeff = self.coeff(release);
+    }
+  }
+
+  #[inline]
+  pub fn set_samplerate(&mut self, samplerate: f32) {
+    self.samplerate = samplerate;
+    self.attack_coeff = self.coeff(self.attack);
+    self.release_coeff = self.coeff(self.release);
+  }
+
+  #[inline]
+  pub fn reset(&mut self) {
+    self.envelope = 0.0;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn follows_step() {
+    let mut env = Follower::new(0.001, 0.1);
+    env.set_samplerate(48000.0);
+    let mut out = 0.0;
+    for _ in 0..480 { out = env.play(1.0); }
+    assert!(out > 0.99);
+    for _ in 0..480 { out = env.play(0.0); }
+    // release is a hundred times slower than attack
+    assert!(out > 0.8 && out < 1.0);
+  }
+
+  #[test]
+  fn set_times_changes_release() {
+    let mut env = Follower::new(0.001, 0.1);
+    env.set_samplerate(48000.0);
+    env.set_times(0.001, 0.001);
+    for _ in 0..480 { env.play(1.0); }
+    let mut out = 0.0;
+    for _ in 0..480 { out = env.play(0.0); }
+    assert!(out < 0.01);
+  }
+}
@@ -0,0 +1,88 @@
+/// Gain compensation for overlapping grains.
+///
+/// Uncorrelated grains add up in power, so `n` overlapping grains are scaled by `1 / sqrt(n)`.
+/// The gain glides towards its target to avoid zipper noise when the overlap changes.
+pub struct AutoGain {
+  gain: f32,
+  coeff: f32,
+}
+
+impl AutoGain {
+  pub fn new() -> Self {
+    Self { gain: 1.0, coeff: 0.0 }
+  }
+
+  /// Gain for an expected number of overlapping grains.
+  #[inline]
+  pub fn target(overlap: f32) -> f32 {
+    overlap.max(1.0).sqrt().recip()
+  }
+
+  /// `overlap` is the expected number of grains playing at once.
+  #[inline]
+  pub fn play(&mut self, overlap: f32) -> f32 {
+    let target = Self::target(overlap);
+    self.gain = target + self.coeff * (self.gain - target);
+    self.gain
+  }
+
+  #[inline]
+  pub fn set_samplerate(&mut self, samplerate: f32) {
+    // ~50 ms glide
+    self.coeff = (-1.0 / (0.05 * samplerate)).exp();
+  }
+
+  #[inline]
+  pub fn reset(&mut self) {
+    self.gain = 1.0;
+  }
+}
+
+/// Level where the soft clipper starts to bend the signal.
+const KNEE: f32 = 0.8;
+
+/// Leaves the signal untouched below `KNEE`, and bends it smoothly towards `1.0` above it.
+#[inline]
+pub fn soft_clip(x: f32) -> f32 {
+  let abs = x.abs();
+  if abs <= KNEE { return x }
+  let bent = KNEE + (1.0 - KNEE) * ((abs - KNEE) / (1.0 - KNEE)).tanh();
+  bent.copysign(x)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn target_follows_overlap() {
+    assert_eq!(AutoGain::target(0.1), 1.0);
+    assert_eq!(AutoGain::target(1.0), 1.0);
+    assert_eq!(AutoGain::target(4.0), 0.5);
+  }
+
+  #[test]
+  fn glides_to_target() {
+    let mut gain = AutoGain::new();
+    gain.set_samplerate(48000.0);
+    let first = gain.play(16.0);
+    assert!(first > 0.9);
+    let mut out = first;
+    for _ in 0..48000 { out = gain.play(16.0); }
+    assert!((out - 0.25).abs() < 1e-3);
+  }
+
+  #[test]
+  fn soft_clip_is_bounded_and_monotonic() {
+    let mut prev = soft_clip(-100.0);
+    for i in -10000..=10000 {
+      let x = i as f32 * 0.01;
+      let y = soft_clip(x);
+      assert!(y.abs() <= 1.0);
+      assert!(y >= prev);
+      prev = y;
+    }
+    assert_eq!(soft_clip(0.5), 0.5);
+    assert_eq!(soft_clip(-0.5), -0.5);
+  }
+}
@@ -0,0 +1,327 @@
+use std::f32::consts::{FRAC_PI_4, PI};
+use crate::filter::{Coeffs, Svf};
+use crate::jitter::Edge;
+
+/// Length of the grain envelope table.
+const ENV_SIZE: usize = 512;
+
+/// Everything a grain needs to know when it is triggered, sampled once and then left alone.
+#[derive(Clone, Copy)]
+pub struct GrainParams {
+  /// Start position in the buffer, `(0.0, 1.0)`.
+  pub position: f32,
+  /// Length in seconds.
+  pub duration: f32,
+  /// `-1.0` hard left, `1.0` hard right.
+  pub pan: f32,
+  /// Playback rate, negative rates play backwards.
+  pub rate: f32,
+  /// Offset added to `position`.
+  pub jitter: f32,
+  /// Wraps or clamps a start outside of the buffer.
+  pub edge: Edge,
+  /// Linear gain.
+  pub gain: f32,
+  /// Where the envelope peaks, `0.5` is a symmetric hann window.
+  pub shape: f32,
+  /// Filter applied to this grain only.
+  pub filter: Coeffs,
+  /// Amount sent to the reverb, `(0.0, 1.0)`.
+  pub send: f32,
+}
+
+/// Output of the granulator, the grains and what they send to the reverb.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct GrainOut {
+  pub main: [f32; 2],
+  pub send: [f32; 2],
+}
+
+#[derive(Clone, Copy)]
+struct Grain {
+  /// Samples left to play, `0` when the voice is free.
+  remaining: u32,
+  position: f32,
+  rate: f32,
+  env_position: f32,
+  env_inc: f32,
+  peak: f32,
+  gain_l: f32,
+  gain_r: f32,
+  coeffs: Coeffs,
+  filter: Svf,
+  send: f32,
+}
+
+impl Grain {
+  const IDLE: Self = Self {
+    remaining: 0,
+    position: 0.0,
+    rate: 0.0,
+    env_position: 0.0,
+    env_inc: 0.0,
+    peak: 0.5,
+    gain_l: 0.0,
+    gain_r: 0.0,
+    coeffs: Coeffs::OFF,
+    filter: Svf::new(),
+    send: 0.0,
+  };
+}
+
+/// Stereo granulator that renders every grain separately,
+/// so that grains can be processed on their own before they are summed.
+///
+/// Grains read the buffer with linear interpolation under a hann envelope, and are
+/// panned with an equal power law. A new grain is dropped when every voice is busy.
+pub struct Granulator<const NUMGRAINS: usize, const BUFSIZE: usize> {
+  buffer: Vec<f32>,
+  write: usize,
+  recording: bool,
+  grains: [Grain; NUMGRAINS],
+  envelope: [f32; ENV_SIZE],
+  samplerate: f32,
+}
+
+impl<const NUMGRAINS: usize, const BUFSIZE: usize> Granulator<NUMGRAINS, BUFSIZE> {
+  pub fn new() -> Self {
+    Self {
+      buffer: vec![0.0; BUFSIZE],
+      write: 0,
+      recording: true,
+      grains: [Grain::IDLE; NUMGRAINS],
+      // hann window
+      envelope: std::array::from_fn(|i| {
+        0.5 - 0.5 * (2.0 * PI * i as f32 / (ENV_SIZE - 1) as f32).cos()
+      }),
+      samplerate: 0.0,
+    }
+  }
+
+  /// Records into the buffer, returns `None` once the buffer is full.
+  #[inline]
+  pub fn record(&mut self, sample: f32) -> Option<()> {
+    if !self.recording { return None }
+    self.buffer[self.write] = sample;
+    self.write += 1;
+    if self.write >= BUFSIZE {
+      self.write = 0;
+      self.recording = false;
+    }
+    Some(())
+  }
+
+  /// Blends `sample` into the buffer once it is full, the write head keeps looping
+  /// through the buffer while the grains are playing.
+  /// `feedback` in the range `(0.0, 1.0)` is how much of the old content is replaced.
+  #[inline]
+  pub fn overdub(&mut self, sample: f32, feedback: f32) {
+    if self.recording { return }
+    let old = &mut self.buffer[self.write];
+    *old += (sample - *old) * feedback.clamp(0.0, 1.0);
+    self.write = (self.write + 1) % BUFSIZE;
+  }
+
+  /// `true` until the buffer is full.
+  #[inline]
+  pub fn recording(&self) -> bool {
+    self.recording
+  }
+
+  #[inline]
+  pub fn buffer(&self) -> &[f32] {
+    &self.buffer
+  }
+
+  /// Starts recording from the beginning of the buffer.
+  #[inline]
+  pub fn reset_record(&mut self) {
+    self.write = 0;
+    self.recording = true;
+  }
+
+  /// Starts a new grain, if there is a free voice.
+  #[inline]
+  pub fn trigger_new(&mut self, params: GrainParams) {
+    if let Some(grain) = self.grains.iter_mut().find(|g| g.remaining == 0) {
+      let samples = (params.duration * self.samplerate).round().max(1.0);
+      // equal power, from hard left at 0 to hard right at pi / 2
+      let angle = (params.pan.clamp(-1.0, 1.0) + 1.0) * FRAC_PI_4;
+      *grain = Grain {
+        remaining: samples as u32,
+        position: params.edge.place(params.position + params.jitter, BUFSIZE),
+        rate: params.rate,
+        env_position: 0.0,
+        env_inc: 1.0 / samples,
+        peak: params.shape.clamp(0.01, 0.99),
+        gain_l: angle.cos() * params.gain,
+        gain_r: angle.sin() * params.gain,
+        coeffs: params.filter,
+        filter: Svf::new(),
+        send: params.send.clamp(0.0, 1.0),
+      };
+    }
+  }
+
+  /// Sum of all playing grains.
+  #[inline]
+  pub fn play(&mut self) -> GrainOut {
+    let len = BUFSIZE as f32;
+    let mut out = GrainOut::default();
+    for grain in self.grains.iter_mut().filter(|g| g.remaining > 0) {
+      let env = interpolate(&self.envelope, skew(grain.env_position, grain.peak) * (ENV_SIZE - 1) as f32);
+      let sample = interpolate(&self.buffer, grain.position) * env;
+      let sample = grain.filter.process(sample, &grain.coeffs);
+      let (l, r) = (sample * grain.gain_l, sample * grain.gain_r);
+      out.main[0] += l;
+      out.main[1] += r;
+      out.send[0] += l * grain.send;
+      out.send[1] += r * grain.send;
+
+      grain.position = (grain.position + grain.rate).rem_euclid(len);
+      grain.env_position += grain.env_inc;
+      grain.remaining -= 1;
+    }
+    out
+  }
+
+  #[inline]
+  pub fn set_samplerate(&mut self, samplerate: f32) {
+    self.samplerate = samplerate;
+  }
+}
+
+/// Moves the middle of the envelope to `peak`, stretching the rise and the fall to fit.
+#[inline]
+fn skew(position: f32, peak: f32) -> f32 {
+  match position < peak {
+    true  => { 0.5 * position / peak },
+    false => { 0.5 + 0.5 * (position - peak) / (1.0 - peak) }
+  }
+}
+
+/// Linear interpolation, wrapping around the end of `table`.
+#[inline]
+fn interpolate(table: &[f32], position: f32) -> f32 {
+  let len = table.len();
+  let i = position as usize;
+  let frac = position - i as f32;
+  let a = table[i % len];
+  let b = table[(i + 1) % len];
+  a + (b - a) * frac
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SR: f32 = 1000.0;
+
+  fn active<const N: usize, const B: usize>(g: &Granulator<N, B>) -> usize {
+    g.grains.iter().filter(|g| g.remaining > 0).count()
+  }
+
+  fn granulator() -> Granulator<4, 1000> {
+    let mut g = Granulator::new();
+    g.set_samplerate(SR);
+    while g.record(1.0).is_some() {}
+    g
+  }
+
+  fn params() -> GrainParams {
+    GrainParams {
+      position: 0.0,
+      duration: 0.1,
+      pan: 0.0,
+      rate: 1.0,
+      jitter: 0.0,
+      edge: Edge::Wrap,
+      gain: 1.0,
+      shape: 0.5,
+      filter: Coeffs::OFF,
+      send: 0.0,
+    }
+  }
+
+  #[test]
+  fn record_until_full() {
+    let mut g = Granulator::<4, 16>::new();
+    for _ in 0..16 { assert!(g.record(0.5).is_some()); }
+    assert!(g.record(0.5).is_none());
+    g.reset_record();
+    assert!(g.record(0.5).is_some());
+  }
+
+  #[test]
+  fn grain_follows_envelope_and_ends() {
+    let mut g = granulator();
+    g.trigger_new(params());
+    let out: Vec<GrainOut> = (0..100).map(|_| g.play()).collect();
+    assert_eq!(out[0].main, [0.0, 0.0]);
+    assert!((out[50].main[0] - std::f32::consts::FRAC_1_SQRT_2).abs() < 0.01);
+    assert_eq!(active(&g), 0);
+    assert_eq!(g.play(), GrainOut::default());
+  }
+
+  #[test]
+  fn hard_pan() {
+    let mut g = granulator();
+    g.trigger_new(GrainParams { pan: -1.0, ..params() });
+    for _ in 0..100 {
+      let [_, r] = g.play().main;
+      assert_eq!(r, 0.0);
+    }
+  }
+
+  #[test]
+  fn shape_moves_the_peak() {
+    let mut g = granulator();
+    g.trigger_new(GrainParams { shape: 0.2, ..params() });
+    let out: Vec<f32> = (0..100).map(|_| g.play().main[0]).collect();
+    let peak = (0..100).max_by(|a, b| out[*a].total_cmp(&out[*b])).unwrap();
+    assert_eq!(peak, 20);
+  }
+
+  #[test]
+  fn gain_scales_grain() {
+    let mut a = granulator();
+    let mut b = granulator();
+    a.trigger_new(params());
+    b.trigger_new(GrainParams { gain: 0.5, ..params() });
+    for _ in 0..100 {
+      assert_eq!(a.play().main[0] * 0.5, b.play().main[0]);
+    }
+  }
+
+  #[test]
+  fn send_follows_grain() {
+    let mut g = granulator();
+    g.trigger_new(GrainParams { send: 0.5, ..params() });
+    g.trigger_new(params());
+    for _ in 0..100 {
+      let out = g.play();
+      // only the first of the two grains is sent
+      assert!((out.send[0] - out.main[0] * 0.25).abs() < 1e-6);
+    }
+  }
+
+  #[test]
+  fn overdub_after_recording() {
+    let mut g = Granulator::<4, 4>::new();
+    g.overdub(1.0, 1.0);
+    assert_eq!(g.write, 0);
+    while g.record(1.0).is_some() {}
+    for _ in 0..4 { g.overdub(0.0, 0.25); }
+    assert_eq!(g.buffer, vec![0.75; 4]);
+    // keeps looping
+    g.overdub(0.0, 1.0);
+    assert_eq!(g.buffer[0], 0.0);
+  }
+
+  #[test]
+  fn voices_are_limited() {
+    let mut g = granulator();
+    for _ in 0..8 { g.trigger_new(params()); }
+    assert_eq!(active(&g), 4);
+  }
+}
@@ -0,0 +1,73 @@
+use nih_plug::prelude::*;
+use rand::Rng;
+
+use crate::random::gaussian;
+
+/// Direction and distribution of the random position offset.
+#[derive(Enum, PartialEq, Clone, Copy)]
+pub enum JitterMode {
+  Forward,
+  Backward,
+  Bipolar,
+  Gaussian,
+}
+
+/// What happens to a grain that would start outside of the recorded buffer.
+#[derive(Enum, PartialEq, Clone, Copy)]
+pub enum Edge {
+  /// Continues from the other end of the buffer.
+  Wrap,
+  /// Stops at the edge of the buffer.
+  Clamp,
+}
+
+impl JitterMode {
+  /// Random offset within `amount`, relative to the buffer length.
+  /// The gaussian offset has `amount / 2` as its standard deviation.
+  #[inline]
+  pub fn offset(&self, amount: f32, rng: &mut impl Rng) -> f32 {
+    match self {
+      JitterMode::Forward  => { amount * rng.gen::<f32>() },
+      JitterMode::Backward => { -amount * rng.gen::<f32>() },
+      JitterMode::Bipolar  => { amount * rng.gen_range(-1.0..=1.0) },
+      JitterMode::Gaussian => { amount * 0.5 * gaussian(rng) },
+    }
+  }
+}
+
+impl Edge {
+  /// Start of a grain at `position`, in samples of a buffer of `len` samples.
+  #[inline]
+  pub fn place(&self, position: f32, len: usize) -> f32 {
+    let len = len as f32;
+    match self {
+      Edge::Wrap  => { position.rem_euclid(1.0) * len },
+      Edge::Clamp => { (position * len).clamp(0.0, len - 1.0) },
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn offsets_follow_mode() {
+    let mut rng = crate::random::Prng::new(1);
+    for _ in 0..1000 {
+      assert!((0.0..=0.5).contains(&JitterMode::Forward.offset(0.5, &mut rng)));
+      assert!((-0.5..=0.0).contains(&JitterMode::Backward.offset(0.5, &mut rng)));
+      assert!((-0.5..=0.5).contains(&JitterMode::Bipolar.offset(0.5, &mut rng)));
+    }
+    assert_eq!(JitterMode::Gaussian.offset(0.0, &mut rng), 0.0);
+  }
+
+  #[test]
+  fn edges() {
+    assert_eq!(Edge::Wrap.place(1.25, 100), 25.0);
+    assert_eq!(Edge::Wrap.place(-0.25, 100), 75.0);
+    assert_eq!(Edge::Clamp.place(1.25, 100), 99.0);
+    assert_eq!(Edge::Clamp.place(-0.25, 100), 0.0);
+    assert_eq!(Edge::Clamp.place(0.5, 100), 50.0);
+  }
+}
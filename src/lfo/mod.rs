@@ -0,0 +1,143 @@
+use std::sync::Arc;
+use nih_plug::prelude::*;
+use rust_dsp::trig::Trigger;
+
+use crate::{
+  ModShape,
+  multitable::{MultiTable, Wavetables},
+  random::Random,
+  sync::{Division, Feel},
+};
+
+#[derive(Params)]
+pub struct LfoParams {
+  #[id = "freq"]
+  pub freq: FloatParam,
+  #[id = "shape"]
+  pub shape: EnumParam<ModShape>,
+  #[id = "morph"]
+  pub morph: FloatParam,
+  #[id = "sync"]
+  pub sync: BoolParam,
+  #[id = "division"]
+  pub division: EnumParam<Division>,
+  #[id = "feel"]
+  pub feel: EnumParam<Feel>,
+  #[id = "phase"]
+  pub phase: FloatParam,
+  #[id = "retrigger"]
+  pub retrigger: BoolParam,
+}
+
+impl LfoParams {
+  pub fn new(name: &str, freq: f32, shape: ModShape) -> Self {
+    Self {
+      freq: FloatParam::new(
+        format!("{} freq", name),
+        freq,
+        FloatRange::Skewed { min: 0.02, max: 20.0, factor: 0.3 }
+      )
+        .with_smoother(SmoothingStyle::Linear(20.0))
+        .with_value_to_string(Arc::new(|f| { format!("{:.2}", f) }))
+        .with_unit(" Hz"),
+      shape: EnumParam::new(format!("{} shape", name), shape),
+      morph: morph_param(&format!("{} morph", name)),
+      sync: BoolParam::new(format!("{} sync", name), false),
+      division: EnumParam::new(format!("{} division", name), Division::Quarter),
+      feel: EnumParam::new(format!("{} feel", name), Feel::Straight),
+      phase: phase_param(&format!("{} phase", name)),
+      retrigger: BoolParam::new(format!("{} retrigger", name), false),
+    }
+  }
+
+  /// Frequency of the LFO, follows the host tempo when synced.
+  #[inline]
+  pub fn frequency(&self, tempo: Option<f64>) -> f32 {
+    let freq = self.freq.smoothed.next();
+    match (self.sync.value(), tempo) {
+      (true, Some(tempo)) => { self.division.value().frequency(self.feel.value(), tempo) },
+      _ => { freq }
+    }
+  }
+}
+
+/// Position between the wavetables, used by the morph shape.
+pub fn morph_param(name: &str) -> FloatParam {
+  FloatParam::new(name, 0.0, FloatRange::Linear { min: 0.0, max: 3.0 })
+    .with_smoother(SmoothingStyle::Linear(20.0))
+    .with_value_to_string(Arc::new(|m| {
+      let names = ["sine", "tri", "saw", "square"];
+      let i = (m.round() as usize).min(3);
+      if (m - i as f32).abs() < 0.01 { names[i].to_string() } else { format!("{:.2}", m) }
+    }))
+}
+
+/// Phase offset, in the range `(0.0, 1.0)`, displayed in degrees.
+pub fn phase_param(name: &str) -> FloatParam {
+  FloatParam::new(name, 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+    .with_value_to_string(Arc::new(|p| { format!("{:.0}", p * 360.0) }))
+    .with_unit("°")
+}
+
+/// LFO that can play either one of the wavetables or one of the random shapes.
+pub struct Lfo {
+  table: MultiTable,
+  random: Random,
+}
+
+impl Lfo {
+  pub fn new() -> Self {
+    Self {
+      table: MultiTable::new(),
+      random: Random::new(0.0),
+    }
+  }
+
+  /// `morph` is only used by `ModShape::Morph`.
+  #[inline]
+  pub fn play(
+    &mut self,
+    tables: &Wavetables,
+    shape: ModShape,
+    morph: f32,
+    frequency: f32,
+    phase: f32
+  ) -> f32 {
+    match shape {
+      ModShape::Sine         => { self.table.play(tables, 0.0, frequency, phase) },
+      ModShape::Tri          => { self.table.play(tables, 1.0, frequency, phase) },
+      ModShape::Saw          => { self.table.play(tables, 2.0, frequency, phase) },
+      ModShape::Square       => { self.table.play(tables, 3.0, frequency, phase) },
+      ModShape::Morph        => { self.table.play(tables, morph, frequency, phase) },
+      ModShape::Custom       => { self.table.play_custom(tables, frequency, phase) },
+      ModShape::SampleHold   => { self.random.hold(frequency.recip()) },
+      ModShape::SmoothRandom => { self.random.play(frequency.recip()) },
+      ModShape::Noise        => { self.random.noise() },
+    }
+  }
+
+  /// Restarts the LFO, random shapes draw a new value.
+  #[inline]
+  pub fn reset(&mut self) {
+    self.table.reset();
+    self.random.reset();
+  }
+
+  /// Restarts the random shapes from `seed`.
+  #[inline]
+  pub fn reseed(&mut self, seed: u64) {
+    self.random.reseed(seed);
+  }
+
+  /// Puts the random shapes back to where they start, the table keeps running.
+  #[inline]
+  pub fn restart(&mut self) {
+    self.random.restart();
+  }
+
+  #[inline]
+  pub fn set_samplerate(&mut self, samplerate: f32) {
+    self.table.set_samplerate(samplerate);
+    self.random.set_samplerate(samplerate);
+  }
+}
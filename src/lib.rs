@@ -1,24 +1,37 @@
 mod editor;
 mod multitable;
+mod random;
+mod lfo;
+mod follower;
+mod modmatrix;
+mod sync;
+mod custom;
+mod gain;
+mod filter;
+mod grains;
+mod reverb;
+mod shimmer;
+mod sequencer;
+mod ratchet;
+mod transient;
+mod cloud;
+mod jitter;
+mod scale;
+mod tuning;
+mod detect;
+mod pan;
 
+use std::path::PathBuf;
 use std::sync::Arc;
 use rand::Rng;
 
 use nih_plug::prelude::*;
+use nih_plug::wrapper::state::{ParamValue, PluginState};
 use nih_plug_vizia::ViziaState;
 
 use rust_dsp::{
-  grains::{
-    GrainTrait,
-    stereo::Granulator
-  },
-  envelope::EnvType,
-  waveshape::traits::Waveshape,
-  interpolation::Linear,
-  trig::{Dust, Impulse, Trigger},
-  noise::Noise,
+  trig::Trigger,
   midibitfield::MidiBitField,
-  dsp::math::midi_to_rate
 };
 
 /* 
@@ -30,8 +43,27 @@ use rust_dsp::{
  *
  * */
 
-use crate::multitable::MultiTable;
-// use crate::random::Random;
+use crate::lfo::{Lfo, LfoParams, phase_param, morph_param};
+use crate::multitable::Wavetables;
+use crate::random::{Prng, Random};
+use crate::follower::Follower;
+use crate::modmatrix::{ModSlotParams, Sources, Destinations, SLOTS, modulate};
+use crate::sync::{Clock, Division, Feel};
+use crate::custom::CustomShape;
+use crate::gain::{AutoGain, soft_clip};
+use crate::filter::{Coeffs, FilterMode, Svf};
+use crate::grains::{Granulator, GrainParams};
+use crate::reverb::Reverb;
+use crate::shimmer::PitchShift;
+use crate::sequencer::{euclidean, Sequencer, StepParams, STEPS};
+use crate::ratchet::Ratchet;
+use crate::transient::Transient;
+use crate::cloud::{Cloud, Density};
+use crate::jitter::{Edge, JitterMode};
+use crate::scale::{quantize, root_param, Scale, ScaleNoteParams};
+use crate::tuning::{key_param, Tuning};
+use crate::detect::Detector;
+use crate::pan::{PanMode, Panner};
 
 const SIZE: usize = 1<<13;
 const MIDI: usize = 1<<7;
@@ -39,27 +71,87 @@ const MIDI: usize = 1<<7;
 pub struct Havregryn<const NUMGRAINS: usize, const BUFSIZE: usize> {
   params:          Arc<HavregrynParams>,
   granulator:      Granulator<NUMGRAINS, BUFSIZE>,
-  rate_modulator:  MultiTable,
-  rate_random_mod: Noise,
-  sin:             [f32; SIZE],
-  tri:             [f32; SIZE],
-  saw:             [f32; SIZE],
-  sqr:             [f32; SIZE],
-  imp:             Impulse,
-  dust:            Dust,
+  rate_modulator:  Lfo,
+  tables:          Wavetables,
+  lfo1:            Lfo,
+  lfo2:            Lfo,
+  random_mod:      Random,
+  rng:             Prng,
+  /// Seed the generators were last seeded with.
+  seed:            i32,
+  follower:        Follower,
+  sources:         Sources,
+  was_playing:     bool,
+  custom_version:  u32,
+  /// Tuning version, A4, root key and detected pitch `midi_rates` was built from.
+  tuning:          (u32, f32, i32, f32),
+  /// Pitch of the captured buffer, shared with the background task and the editor.
+  detector:        Arc<Detector>,
+  /// Where the next chunk of a finished capture is copied to the detector from.
+  copying:         Option<usize>,
+  auto_gain:       AutoGain,
+  held:            usize,
+  post_filter:     [Svf; 2],
+  /// Coefficients of the post filter, and the mode, cutoff and resonance they were made from.
+  post_coeffs:     Coeffs,
+  post_key:        (FilterMode, f32, f32),
+  reverb:          Reverb,
+  shimmer:         PitchShift,
+  cloud:           Cloud,
+  clock:           Clock,
+  sequencer:       Sequencer,
+  ratchet:         Ratchet,
+  transient:       Transient,
+  panner:          Panner,
+  /// Step of the last trigger, kept for the rest of a ratchet burst.
+  last_step:       Option<usize>,
   start_bool:      bool,
+  /// `1.0` while the buffer is recording, the dry signal is passed through at unity then.
+  capture:         Smoother<f32>,
+  recording:       bool,
   sr_recip:        f32,
   pitches:        MidiBitField,
   midi_rates:      [f32; MIDI],
 }
 
-#[derive(Enum, PartialEq)]
-pub(crate) enum ModShape {
+#[derive(Enum, PartialEq, Clone, Copy)]
+pub enum ModShape {
   Sine,
   Tri,
   Saw,
   Square,
-  // RANDOM
+  #[name = "S&H"]
+  SampleHold,
+  #[name = "Smooth"]
+  SmoothRandom,
+  Noise,
+  Morph,
+  Custom,
+}
+
+/// Playback direction of the grains.
+#[derive(Enum, PartialEq, Clone, Copy)]
+pub enum Direction {
+  Forward,
+  Reverse,
+}
+
+/// Work done off the audio thread.
+pub enum Task {
+  /// Loads a Scala `.scl` scale or `.kbm` keyboard mapping.
+  LoadTuning(PathBuf),
+  /// Back to twelve tone equal temperament.
+  ClearTuning,
+  /// Detects the pitch of the last capture.
+  DetectPitch,
+}
+
+/// Signal the envelope follower listens to.
+#[derive(Enum, PartialEq, Clone, Copy)]
+pub enum EnvInput {
+  Main,
+  Aux,
+  Both,
 }
 
 #[derive(Params)]
@@ -70,6 +162,12 @@ pub struct HavregrynParams {
   /// gain parameter is stored as linear gain while the values are displayed in decibels.
   #[persist = "editor-state"]
   pub editor_state: Arc<ViziaState>,
+  /// Breakpoints of the `ModShape::Custom` LFO shape.
+  #[persist = "custom-shape"]
+  pub custom_shape: CustomShape,
+  /// Scala scale and keyboard mapping of the MIDI keys.
+  #[persist = "tuning"]
+  pub tuning: Tuning,
 
   #[id = "position"]
   pub position: FloatParam,
@@ -77,23 +175,208 @@ pub struct HavregrynParams {
   pub duration: FloatParam,
   #[id = "jitter"]
   pub jitter: FloatParam,
+  #[id = "jitter-mode"]
+  pub jitter_mode: EnumParam<JitterMode>,
+  /// Wraps or clamps grains jittered past the edges of the buffer.
+  #[id = "jitter-edge"]
+  pub jitter_edge: EnumParam<Edge>,
   #[id = "trigger"]
   pub trigger: FloatParam,
+  /// Locks the trigger to the host tempo and song position.
+  #[id = "trigger-sync"]
+  pub trigger_sync: BoolParam,
+  #[id = "trigger-division"]
+  pub trigger_division: EnumParam<Division>,
+  #[id = "trigger-feel"]
+  pub trigger_feel: EnumParam<Feel>,
+  /// Delays every second synced trigger.
+  #[id = "trigger-swing"]
+  pub trigger_swing: FloatParam,
+  /// Chance of a trigger starting grains.
+  #[id = "trigger-probability"]
+  pub trigger_probability: FloatParam,
+  /// Number of evenly spaced grains started by every trigger.
+  #[id = "ratchet"]
+  pub ratchet: IntParam,
+
+  /// Triggers the grains on transients in the aux input instead.
+  #[id = "transient-on"]
+  pub transient_on: BoolParam,
+  #[id = "transient-threshold"]
+  pub transient_threshold: FloatParam,
+  #[id = "transient-sensitivity"]
+  pub transient_sensitivity: FloatParam,
+  /// Shortest time between two transients.
+  #[id = "transient-hold"]
+  pub transient_hold: FloatParam,
+
+  /// Triggers the grains from the step sequencer instead.
+  #[id = "seq-on"]
+  pub seq_on: BoolParam,
+  #[id = "seq-length"]
+  pub seq_length: IntParam,
+  #[id = "seq-division"]
+  pub seq_division: EnumParam<Division>,
+  /// Replaces the on switches of the steps with an euclidean pattern.
+  #[id = "seq-euclid"]
+  pub seq_euclid: BoolParam,
+  #[id = "seq-pulses"]
+  pub seq_pulses: IntParam,
+  #[id = "seq-rotate"]
+  pub seq_rotate: IntParam,
+  #[nested(array, group = "steps")]
+  pub steps: [StepParams; STEPS],
   
   #[id = "spread"]
   pub spread: FloatParam,
+  #[id = "pan-mode"]
+  pub pan_mode: EnumParam<PanMode>,
+  /// Stereo width of the grains, `0.0` is mono.
+  #[id = "width"]
+  pub width: FloatParam,
 
-  #[id = "rate"]
-  pub rate: FloatParam,
-  #[id = "rate-mod-amount"]
-  pub rate_mod_amount: FloatParam,
+  /// Pitch of the grains in semitones, `0` plays at the recorded pitch.
+  #[id = "rate-coarse"]
+  pub coarse: FloatParam,
+  /// Fine tuning in cents.
+  #[id = "rate-fine"]
+  pub fine: FloatParam,
+  #[id = "direction"]
+  pub direction: EnumParam<Direction>,
+  /// Chance of a grain playing against `direction`.
+  #[id = "reverse"]
+  pub reverse: FloatParam,
+  /// Snaps the pitch of every grain to a scale.
+  #[id = "scale"]
+  pub scale: EnumParam<Scale>,
+  #[id = "scale-root"]
+  pub scale_root: IntParam,
+  /// Chance of a grain jumping an octave up or down.
+  #[id = "octave-jump"]
+  pub octave_jump: FloatParam,
+  /// Pitch classes of the user defined scale, counted from the root.
+  #[nested(array, group = "user scale")]
+  pub user_scale: [ScaleNoteParams; 12],
+  /// Reference frequency of the tuning.
+  #[id = "a4"]
+  pub a4: FloatParam,
+  /// Key that plays the recording at its own pitch.
+  #[id = "root-key"]
+  pub root_key: IntParam,
+  /// Plays every key at its own pitch, from the detected pitch of the recording.
+  #[id = "auto-root"]
+  pub auto_root: BoolParam,
+
+  /// Depth of the rate modulator, in semitones.
+  #[id = "rate-mod-depth"]
+  pub rate_mod_depth: FloatParam,
   #[id = "rate-mod-shape"]
   rate_mod_shape: EnumParam<ModShape>,
+  #[id = "rate-mod-morph"]
+  pub rate_mod_morph: FloatParam,
   #[id = "rate-mod-freq"]
   pub rate_mod_freq: FloatParam,
+  #[id = "rate-mod-sync"]
+  pub rate_mod_sync: BoolParam,
+  #[id = "rate-mod-division"]
+  pub rate_mod_division: EnumParam<Division>,
+  #[id = "rate-mod-feel"]
+  pub rate_mod_feel: EnumParam<Feel>,
+  #[id = "rate-mod-phase"]
+  pub rate_mod_phase: FloatParam,
+  #[id = "rate-mod-retrigger"]
+  pub rate_mod_retrigger: BoolParam,
+
+  #[nested(id_prefix = "lfo1", group = "lfo 1")]
+  pub lfo1: LfoParams,
+  #[nested(id_prefix = "lfo2", group = "lfo 2")]
+  pub lfo2: LfoParams,
+  /// Restarts all LFOs when the host transport starts playing.
+  #[id = "lfo-reset-on-play"]
+  pub lfo_reset_on_play: BoolParam,
+  /// Seed of all randomness, the same seed always renders the same grains.
+  #[id = "seed"]
+  pub seed: IntParam,
+  #[id = "reseed-on-play"]
+  pub reseed_on_play: BoolParam,
+  #[id = "random-freq"]
+  pub random_freq: FloatParam,
+
+  #[id = "env-attack"]
+  pub env_attack: FloatParam,
+  #[id = "env-release"]
+  pub env_release: FloatParam,
+  #[id = "env-input"]
+  pub env_input: EnumParam<EnvInput>,
+
+  #[nested(array, group = "mod matrix")]
+  pub mod_slots: [ModSlotParams; SLOTS],
+
+  #[id = "filter-mode"]
+  pub filter_mode: EnumParam<FilterMode>,
+  #[id = "filter-cutoff"]
+  pub filter_cutoff: FloatParam,
+  #[id = "filter-resonance"]
+  pub filter_resonance: FloatParam,
+  /// Filters every grain on its own, instead of the summed output.
+  #[id = "filter-per-grain"]
+  pub filter_per_grain: BoolParam,
+  /// Random offset of the cutoff of each grain, in octaves.
+  #[id = "filter-random"]
+  pub filter_random: FloatParam,
+
+  #[id = "reverb-size"]
+  pub reverb_size: FloatParam,
+  #[id = "reverb-damping"]
+  pub reverb_damping: FloatParam,
+  /// Level of the reverb return, mixed with the grains.
+  #[id = "reverb-mix"]
+  pub reverb_mix: FloatParam,
+  /// Amount each new grain sends to the reverb.
+  #[id = "reverb-send"]
+  pub reverb_send: FloatParam,
+  /// Random offset of the send of each grain.
+  #[id = "reverb-send-random"]
+  pub reverb_send_random: FloatParam,
+
+  /// Amount of grain output re-recorded into the buffer.
+  #[id = "shimmer-feedback"]
+  pub shimmer_feedback: FloatParam,
+  /// Pitch of the re-recorded grains, in semitones.
+  #[id = "shimmer-interval"]
+  pub shimmer_interval: IntParam,
 
-  #[id = "random"]
-  pub random: BoolParam,
+  #[id = "mix"]
+  pub mix: FloatParam,
+  #[id = "gain"]
+  pub gain: FloatParam,
+  /// Scales the grains down as more of them overlap.
+  #[id = "auto-gain"]
+  pub auto_gain: BoolParam,
+  #[id = "soft-clip"]
+  pub soft_clip: BoolParam,
+
+  /// Where the grain envelope peaks.
+  #[id = "env-shape"]
+  pub env_shape: FloatParam,
+  /// Random ranges, every grain draws its own values within them.
+  #[id = "duration-random"]
+  pub duration_random: FloatParam,
+  #[id = "rate-random-semitones"]
+  pub rate_random_semitones: FloatParam,
+  #[id = "rate-random-cents"]
+  pub rate_random_cents: FloatParam,
+  #[id = "amp-random"]
+  pub amp_random: FloatParam,
+  #[id = "env-shape-random"]
+  pub env_shape_random: FloatParam,
+
+  /// Distribution of the time between triggers.
+  #[id = "density"]
+  pub density: EnumParam<Density>,
+  /// Spread of the jittered and gaussian trigger intervals.
+  #[id = "density-deviation"]
+  pub deviation: FloatParam,
   #[id = "resample"]
   pub resample: BoolParam,
 
@@ -103,25 +386,46 @@ pub struct HavregrynParams {
 
 impl<const NUMGRAINS: usize, const BUFSIZE: usize> Default for Havregryn<NUMGRAINS, BUFSIZE> { 
   fn default() -> Self { 
-    let env_shape: EnvType = EnvType::Vector([0.0;512].hanning().to_vec());
     Self {
       params: Arc::new(HavregrynParams::default()),
-      sin: [0.0; SIZE].sine(),
-      tri: [0.0; SIZE].triangle(),
-      saw: [0.0; SIZE].sawtooth(),
-      sqr: [0.0; SIZE].square(),
+      tables:           Wavetables::new(),
       // rate_modulator: WaveTable::<WT_BUFSIZE>::new(sin.borrow_mut(), 0.0),
-      rate_modulator:   MultiTable::new(),
-      rate_random_mod:  Noise::new(0.0),
-      granulator:       Granulator::new(&env_shape, 0.0),
-      imp:              Impulse::new(0.0),
-      dust:             Dust::new(0.0),
+      rate_modulator:   Lfo::new(),
+      lfo1:             Lfo::new(),
+      lfo2:             Lfo::new(),
+      random_mod:       Random::new(0.0),
+      rng:              Prng::new(0),
+      seed:             0,
+      follower:         Follower::new(0.01, 0.2),
+      sources:          Sources::default(),
+      was_playing:      false,
+      custom_version:   0,
+      tuning:           (0, 0.0, 0, 0.0),
+      detector:         Arc::new(Detector::new(BUFSIZE)),
+      copying:          None,
+      auto_gain:        AutoGain::new(),
+      held:             0,
+      post_filter:      [Svf::new(); 2],
+      post_coeffs:      Coeffs::OFF,
+      post_key:         (FilterMode::Off, 0.0, 0.0),
+      reverb:           Reverb::new(),
+      shimmer:          PitchShift::new(),
+      granulator:       Granulator::new(),
+      cloud:            Cloud::new(),
+      clock:            Clock::new(),
+      sequencer:        Sequencer::new(),
+      ratchet:          Ratchet::new(),
+      transient:        Transient::new(),
+      panner:           Panner::new(),
+      last_step:        None,
       // sample_color_active: Color::rgba(0xff, 0x25, 0x5c, 0x00),
       // sample_color_deactive: Color::rgba(0xfa, 0xfa, 0xfa, 0x00),
       sr_recip:         0.0,
       start_bool:       true,
+      capture:          Smoother::new(SmoothingStyle::Linear(20.0)),
+      recording:        false,
       pitches:          MidiBitField::new(),
-      midi_rates:       std::array::from_fn(|i| midi_to_rate(i as u8)),
+      midi_rates:       [1.0; MIDI],
     }
   }
 }
@@ -130,6 +434,8 @@ impl Default for HavregrynParams {
   fn default() -> Self {
     Self {
       editor_state: editor::default_state(),
+      custom_shape: CustomShape::default(),
+      tuning: Tuning::default(),
 
       position: FloatParam::new(
         "position", 
@@ -146,6 +452,9 @@ impl Default for HavregrynParams {
         .with_smoother(SmoothingStyle::Linear(20.0))
         .with_value_to_string(Arc::new(|i| { format!("{:.2}", i) })),
 
+      jitter_mode: EnumParam::new("jitter mode", JitterMode::Bipolar),
+      jitter_edge: EnumParam::new("jitter edge", Edge::Wrap),
+
       duration: FloatParam::new(
         "grain length", 
         0.2, 
@@ -161,6 +470,70 @@ impl Default for HavregrynParams {
       )
         .with_value_to_string(Arc::new(|i| { format!("{:.2}", i) }))
         .with_unit(" sec"),
+
+      trigger_sync: BoolParam::new("trigger sync", false),
+      trigger_division: EnumParam::new("trigger division", Division::Eighth),
+      trigger_feel: EnumParam::new("trigger feel", Feel::Straight),
+      trigger_swing: FloatParam::new(
+        "swing",
+        0.0,
+        FloatRange::Linear { min: 0.0, max: 1.0 }
+      )
+        .with_value_to_string(formatters::v2s_f32_percentage(0))
+        .with_string_to_value(formatters::s2v_f32_percentage())
+        .with_unit("%"),
+
+      trigger_probability: FloatParam::new(
+        "probability",
+        1.0,
+        FloatRange::Linear { min: 0.0, max: 1.0 }
+      )
+        .with_value_to_string(formatters::v2s_f32_percentage(0))
+        .with_string_to_value(formatters::s2v_f32_percentage())
+        .with_unit("%"),
+
+      ratchet: IntParam::new("ratchet", 1, IntRange::Linear { min: 1, max: 8 }),
+
+      transient_on: BoolParam::new("sidechain", false),
+
+      transient_threshold: FloatParam::new(
+        "threshold",
+        util::db_to_gain(-24.0),
+        FloatRange::Skewed {
+          min: util::db_to_gain(-60.0),
+          max: util::db_to_gain(0.0),
+          factor: FloatRange::gain_skew_factor(-60.0, 0.0),
+        }
+      )
+        .with_value_to_string(formatters::v2s_f32_gain_to_db(1))
+        .with_string_to_value(formatters::s2v_f32_gain_to_db())
+        .with_unit(" dB"),
+
+      transient_sensitivity: FloatParam::new(
+        "sensitivity",
+        0.5,
+        FloatRange::Linear { min: 0.0, max: 1.0 }
+      )
+        .with_value_to_string(Arc::new(|i| { format!("{:.2}", i) })),
+
+      transient_hold: FloatParam::new(
+        "hold",
+        0.05,
+        FloatRange::Skewed { min: 0.005, max: 1.0, factor: 0.3 }
+      )
+        .with_value_to_string(Arc::new(|t| { format!("{:.0}", t * 1000.0) }))
+        .with_string_to_value(Arc::new(|s| {
+          s.trim().trim_end_matches("ms").trim().parse::<f32>().ok().map(|t| t / 1000.0)
+        }))
+        .with_unit(" ms"),
+
+      seq_on: BoolParam::new("sequencer", false),
+      seq_length: IntParam::new("seq length", 16, IntRange::Linear { min: 1, max: STEPS as i32 }),
+      seq_division: EnumParam::new("seq division", Division::Sixteenth),
+      seq_euclid: BoolParam::new("euclid", false),
+      seq_pulses: IntParam::new("pulses", 5, IntRange::Linear { min: 0, max: STEPS as i32 }),
+      seq_rotate: IntParam::new("rotate", 0, IntRange::Linear { min: 0, max: STEPS as i32 - 1 }),
+      steps: std::array::from_fn(|i| StepParams::new(i + 1)),
       
       spread: FloatParam::new(
         "stereo spread", 
@@ -169,13 +542,64 @@ impl Default for HavregrynParams {
       )
         .with_smoother(SmoothingStyle::Linear(20.0))
         .with_value_to_string(Arc::new(|i| { format!("{:.2}", i) })),
-
-      rate: FloatParam::new(
-        "speed",
+      pan_mode: EnumParam::new("pan mode", PanMode::Random),
+      width: FloatParam::new(
+        "width",
         1.0,
-        FloatRange::Linear { min: -1.0, max: 1.0 }
-      ) 
-        .with_value_to_string(Arc::new(|i| { format!("{:.2}", i) })),
+        FloatRange::Linear { min: 0.0, max: 2.0 }
+      )
+        .with_smoother(SmoothingStyle::Linear(20.0))
+        .with_value_to_string(formatters::v2s_f32_percentage(0))
+        .with_string_to_value(formatters::s2v_f32_percentage())
+        .with_unit("%"),
+
+      coarse: FloatParam::new(
+        "coarse",
+        0.0,
+        FloatRange::Linear { min: -48.0, max: 48.0 }
+      )
+        .with_step_size(1.0)
+        .with_unit(" st"),
+
+      fine: FloatParam::new(
+        "fine",
+        0.0,
+        FloatRange::Linear { min: -100.0, max: 100.0 }
+      )
+        .with_value_to_string(Arc::new(|i| { format!("{:.0}", i) }))
+        .with_unit(" ct"),
+
+      direction: EnumParam::new("direction", Direction::Forward),
+      reverse: FloatParam::new(
+        "reverse",
+        0.0,
+        FloatRange::Linear { min: 0.0, max: 1.0 }
+      )
+        .with_value_to_string(formatters::v2s_f32_percentage(0))
+        .with_string_to_value(formatters::s2v_f32_percentage())
+        .with_unit("%"),
+
+      scale: EnumParam::new("scale", Scale::Off),
+      scale_root: root_param(),
+      octave_jump: FloatParam::new(
+        "octave jump",
+        0.0,
+        FloatRange::Linear { min: 0.0, max: 1.0 }
+      )
+        .with_value_to_string(formatters::v2s_f32_percentage(0))
+        .with_string_to_value(formatters::s2v_f32_percentage())
+        .with_unit("%"),
+      user_scale: std::array::from_fn(ScaleNoteParams::new),
+      a4: FloatParam::new(
+        "A4",
+        440.0,
+        FloatRange::Linear { min: 400.0, max: 480.0 }
+      )
+        .with_step_size(0.1)
+        .with_value_to_string(Arc::new(|f| { format!("{:.1}", f) }))
+        .with_unit(" Hz"),
+      root_key: key_param(),
+      auto_root: BoolParam::new("auto root", false),
 
       rate_mod_freq: FloatParam::new(
         "mod freq",
@@ -186,31 +610,295 @@ impl Default for HavregrynParams {
         .with_value_to_string(Arc::new(|f| { format!("{:.2}", f) }))
         .with_unit(" Hz"),
 
-      rate_mod_amount: FloatParam::new(
-        "mod amount",
+      rate_mod_depth: FloatParam::new(
+        "mod depth",
         0.0,
-        FloatRange::Skewed { min: 0.002, max: 1.0, factor: 0.46 }
+        FloatRange::Skewed { min: 0.0, max: 24.0, factor: 0.5 }
       )
-        .with_smoother(SmoothingStyle::Logarithmic(50.0))
-        .with_value_to_string(Arc::new(|i| { format!("{:.2}", i) })),
+        .with_smoother(SmoothingStyle::Linear(50.0))
+        .with_value_to_string(Arc::new(|i| { format!("{:.2}", i) }))
+        .with_unit(" st"),
 
       rate_mod_shape: EnumParam::new("mod shape", ModShape::Sine),
+      rate_mod_morph: morph_param("mod morph"),
+      rate_mod_sync: BoolParam::new("mod sync", false),
+      rate_mod_division: EnumParam::new("mod division", Division::Sixteenth),
+      rate_mod_feel: EnumParam::new("mod feel", Feel::Straight),
+      rate_mod_phase: phase_param("mod phase"),
+      rate_mod_retrigger: BoolParam::new("mod retrigger", false),
+
+      lfo1: LfoParams::new("lfo 1", 1.0, ModShape::Sine),
+      lfo2: LfoParams::new("lfo 2", 0.25, ModShape::Tri),
+      lfo_reset_on_play: BoolParam::new("lfo reset on play", false),
+      seed: IntParam::new("seed", 0, IntRange::Linear { min: 0, max: 9999 }),
+      reseed_on_play: BoolParam::new("reseed on play", false),
+
+      random_freq: FloatParam::new(
+        "random freq",
+        2.0,
+        FloatRange::Skewed { min: 0.02, max: 20.0, factor: 0.3 }
+      )
+        .with_smoother(SmoothingStyle::Linear(20.0))
+        .with_value_to_string(Arc::new(|f| { format!("{:.2}", f) }))
+        .with_unit(" Hz"),
+
+      env_attack: FloatParam::new(
+        "env attack",
+        0.01,
+        FloatRange::Skewed { min: 0.001, max: 1.0, factor: 0.3 }
+      )
+        .with_value_to_string(Arc::new(|t| { format!("{:.0}", t * 1000.0) }))
+        .with_string_to_value(Arc::new(|s| {
+          s.trim().trim_end_matches("ms").trim().parse::<f32>().ok().map(|t| t / 1000.0)
+        }))
+        .with_unit(" ms"),
+
+      env_release: FloatParam::new(
+        "env release",
+        0.2,
+        FloatRange::Skewed { min: 0.01, max: 5.0, factor: 0.3 }
+      )
+        .with_value_to_string(Arc::new(|t| { format!("{:.0}", t * 1000.0) }))
+        .with_string_to_value(Arc::new(|s| {
+          s.trim().trim_end_matches("ms").trim().parse::<f32>().ok().map(|t| t / 1000.0)
+        }))
+        .with_unit(" ms"),
+
+      env_input: EnumParam::new("env input", EnvInput::Both),
+
+      mod_slots: std::array::from_fn(|i| ModSlotParams::new(i + 1)),
+
+      filter_mode: EnumParam::new("filter", FilterMode::Off),
+
+      filter_cutoff: FloatParam::new(
+        "cutoff",
+        2000.0,
+        FloatRange::Skewed { min: 20.0, max: 20000.0, factor: FloatRange::skew_factor(-2.0) }
+      )
+        .with_smoother(SmoothingStyle::Logarithmic(20.0))
+        .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+        .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+
+      filter_resonance: FloatParam::new(
+        "resonance",
+        0.1,
+        FloatRange::Linear { min: 0.0, max: 1.0 }
+      )
+        .with_smoother(SmoothingStyle::Linear(20.0))
+        .with_value_to_string(Arc::new(|i| { format!("{:.2}", i) })),
+
+      filter_per_grain: BoolParam::new("per grain", false),
+
+      filter_random: FloatParam::new(
+        "cutoff random",
+        0.0,
+        FloatRange::Linear { min: 0.0, max: 4.0 }
+      )
+        .with_value_to_string(Arc::new(|i| { format!("{:.2}", i) }))
+        .with_unit(" oct"),
+
+      reverb_size: FloatParam::new(
+        "reverb size",
+        0.5,
+        FloatRange::Linear { min: 0.0, max: 1.0 }
+      )
+        .with_smoother(SmoothingStyle::Linear(50.0))
+        .with_value_to_string(Arc::new(|i| { format!("{:.2}", i) })),
+
+      reverb_damping: FloatParam::new(
+        "reverb damping",
+        0.3,
+        FloatRange::Linear { min: 0.0, max: 1.0 }
+      )
+        .with_smoother(SmoothingStyle::Linear(20.0))
+        .with_value_to_string(Arc::new(|i| { format!("{:.2}", i) })),
+
+      reverb_mix: FloatParam::new(
+        "reverb mix",
+        0.0,
+        FloatRange::Linear { min: 0.0, max: 1.0 }
+      )
+        .with_smoother(SmoothingStyle::Linear(20.0))
+        .with_value_to_string(formatters::v2s_f32_percentage(0))
+        .with_string_to_value(formatters::s2v_f32_percentage())
+        .with_unit("%"),
+
+      reverb_send: FloatParam::new(
+        "reverb send",
+        1.0,
+        FloatRange::Linear { min: 0.0, max: 1.0 }
+      )
+        .with_value_to_string(Arc::new(|i| { format!("{:.2}", i) })),
+
+      reverb_send_random: FloatParam::new(
+        "send random",
+        0.0,
+        FloatRange::Linear { min: 0.0, max: 1.0 }
+      )
+        .with_value_to_string(Arc::new(|i| { format!("{:.2}", i) })),
+
+      shimmer_feedback: FloatParam::new(
+        "shimmer",
+        0.0,
+        FloatRange::Linear { min: 0.0, max: 1.0 }
+      )
+        .with_smoother(SmoothingStyle::Linear(20.0))
+        .with_value_to_string(Arc::new(|i| { format!("{:.2}", i) })),
+
+      shimmer_interval: IntParam::new(
+        "interval",
+        12,
+        IntRange::Linear { min: -24, max: 24 }
+      )
+        .with_unit(" st"),
+
+      mix: FloatParam::new(
+        "dry/wet",
+        1.0,
+        FloatRange::Linear { min: 0.0, max: 1.0 }
+      )
+        .with_smoother(SmoothingStyle::Linear(20.0))
+        .with_value_to_string(formatters::v2s_f32_percentage(0))
+        .with_string_to_value(formatters::s2v_f32_percentage())
+        .with_unit("%"),
+
+      gain: FloatParam::new(
+        "output gain",
+        util::db_to_gain(0.0),
+        FloatRange::Skewed {
+          min: util::db_to_gain(-30.0),
+          max: util::db_to_gain(12.0),
+          factor: FloatRange::gain_skew_factor(-30.0, 12.0),
+        }
+      )
+        .with_smoother(SmoothingStyle::Logarithmic(50.0))
+        .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
+        .with_string_to_value(formatters::s2v_f32_gain_to_db())
+        .with_unit(" dB"),
+
+      auto_gain: BoolParam::new("auto gain", false),
+      soft_clip: BoolParam::new("soft clip", false),
 
       resample: BoolParam::new(
         "sample", 
         false
       ),
 
-      random: BoolParam::new(
-        "random", 
-        false
-      ),
+      env_shape: FloatParam::new(
+        "env shape",
+        0.5,
+        FloatRange::Linear { min: 0.0, max: 1.0 }
+      )
+        .with_value_to_string(Arc::new(|i| { format!("{:.2}", i) })),
+
+      duration_random: FloatParam::new(
+        "duration random",
+        0.0,
+        FloatRange::Linear { min: 0.0, max: 1.0 }
+      )
+        .with_value_to_string(formatters::v2s_f32_percentage(0))
+        .with_string_to_value(formatters::s2v_f32_percentage())
+        .with_unit("%"),
+
+      rate_random_semitones: FloatParam::new(
+        "semitone random",
+        0.0,
+        FloatRange::Linear { min: 0.0, max: 24.0 }
+      )
+        .with_step_size(1.0)
+        .with_unit(" st"),
+
+      rate_random_cents: FloatParam::new(
+        "cent random",
+        0.0,
+        FloatRange::Linear { min: 0.0, max: 100.0 }
+      )
+        .with_value_to_string(Arc::new(|i| { format!("{:.0}", i) }))
+        .with_unit(" ct"),
+
+      amp_random: FloatParam::new(
+        "amp random",
+        0.0,
+        FloatRange::Linear { min: 0.0, max: 1.0 }
+      )
+        .with_value_to_string(Arc::new(|i| { format!("{:.2}", i) })),
+
+      env_shape_random: FloatParam::new(
+        "shape random",
+        0.0,
+        FloatRange::Linear { min: 0.0, max: 1.0 }
+      )
+        .with_value_to_string(Arc::new(|i| { format!("{:.2}", i) })),
+
+      density: EnumParam::new("density", Density::Periodic),
+
+      deviation: FloatParam::new(
+        "deviation",
+        0.25,
+        FloatRange::Linear { min: 0.0, max: 1.0 }
+      )
+        .with_value_to_string(formatters::v2s_f32_percentage(0))
+        .with_string_to_value(formatters::s2v_f32_percentage())
+        .with_unit("%"),
 
       // color: AtomicBool::new(false)
     }
   }
 }
 
+impl<const NUMGRAINS: usize, const BUFSIZE: usize> Havregryn<NUMGRAINS, BUFSIZE> {
+  /// Number of held MIDI notes.
+  fn count_held(&self) -> usize {
+    let mut held = 0;
+    self.pitches.notes(&mut |_| held += 1);
+    held
+  }
+
+  /// Restarts every random generator from the seed param.
+  fn reseed(&mut self) {
+    self.seed = self.params.seed.value();
+    let seed = self.seed as u64;
+    self.rng.reseed(seed);
+    // separate streams for the generators that run on their own
+    self.random_mod.reseed(seed.wrapping_add(1 << 32));
+    self.cloud.reseed(seed.wrapping_add(2 << 32));
+    self.rate_modulator.reseed(seed.wrapping_add(3 << 32));
+    self.lfo1.reseed(seed.wrapping_add(4 << 32));
+    self.lfo2.reseed(seed.wrapping_add(5 << 32));
+  }
+
+  /// Puts everything that draws from the generators back to where it starts, so that
+  /// playing from the same position renders the same grains again.
+  fn restart(&mut self) {
+    self.reseed();
+    self.random_mod.restart();
+    self.rate_modulator.restart();
+    self.lfo1.restart();
+    self.lfo2.restart();
+    self.cloud.reset();
+    self.panner.reset();
+    self.ratchet.reset();
+    self.last_step = None;
+  }
+
+  /// Rebuilds `midi_rates` when the tuning, A4, the root key or the detected pitch
+  /// has changed. On the audio thread it does not block, and tries again on the next buffer.
+  fn retune(&mut self, audio_thread: bool) {
+    let p = &self.params;
+    let source = self.detector.frequency().filter(|_| p.auto_root.value());
+    let tuning = (p.tuning.version(), p.a4.value(), p.root_key.value(), source.unwrap_or(0.0));
+    if tuning == self.tuning { return }
+    let state = match audio_thread {
+      true  => { p.tuning.try_state() },
+      false => { Some(p.tuning.state()) }
+    };
+    if let Some(state) = state {
+      state.rates(tuning.1, tuning.2, source, &mut self.midi_rates);
+      self.tuning = tuning;
+    }
+  }
+}
+
 impl<const NUMGRAINS: usize, const BUFSIZE: usize> Plugin for Havregryn<NUMGRAINS, BUFSIZE> {
   const NAME: &'static str = "Havregryn";
   const VENDOR: &'static str = "Viktor Sandström";
@@ -235,7 +923,7 @@ impl<const NUMGRAINS: usize, const BUFSIZE: usize> Plugin for Havregryn<NUMGRAIN
   }];
 
 
-  const MIDI_INPUT: MidiConfig = MidiConfig::Basic;
+  const MIDI_INPUT: MidiConfig = MidiConfig::MidiCCs;
   const MIDI_OUTPUT: MidiConfig = MidiConfig::None;
 
   const SAMPLE_ACCURATE_AUTOMATION: bool = true;
@@ -247,12 +935,54 @@ impl<const NUMGRAINS: usize, const BUFSIZE: usize> Plugin for Havregryn<NUMGRAIN
   // More advanced plugins can use this to run expensive background tasks. See the field's
   // documentation for more information. `()` means that the plugin does not have any backgrouggnd
   // tasks.
-  type BackgroundTask = ();
+  type BackgroundTask = Task;
 
   fn params(&self) -> Arc<dyn Params + 'static> {
       self.params.clone()
   }
 
+  /// Moves the values of removed parameters onto the ones that replaced them.
+  fn filter_state(state: &mut PluginState) {
+    // the random trigger switch drew every interval from up to twice the trigger interval
+    if let Some(ParamValue::Bool(random)) = state.params.remove("random") {
+      if random {
+        state.params.insert("density".to_string(), ParamValue::I32(Density::Jittered.to_index() as i32));
+        state.params.insert("density-deviation".to_string(), ParamValue::F32(1.0));
+      }
+    }
+    // the speed was a signed playback rate, with the rate modulator added on top of it
+    let speed = match state.params.remove("rate") {
+      Some(ParamValue::F32(rate)) => {
+        let direction = match rate < 0.0 {
+          true  => { Direction::Reverse },
+          false => { Direction::Forward }
+        };
+        let semitones = (12.0 * rate.abs().log2()).clamp(-48.0, 48.0);
+        state.params.insert("direction".to_string(), ParamValue::I32(direction.to_index() as i32));
+        state.params.insert("rate-coarse".to_string(), ParamValue::F32(semitones.round()));
+        state.params.insert("rate-fine".to_string(), ParamValue::F32((semitones - semitones.round()) * 100.0));
+        rate.abs()
+      },
+      _ => { 1.0 }
+    };
+    if let Some(ParamValue::F32(amount)) = state.params.remove("rate-mod-amount") {
+      let depth = 12.0 * (1.0 + amount / speed.max(0.001)).log2();
+      state.params.insert("rate-mod-depth".to_string(), ParamValue::F32(depth.clamp(0.0, 24.0)));
+    }
+  }
+
+  fn task_executor(&mut self) -> TaskExecutor<Self> {
+    let params = self.params.clone();
+    let detector = self.detector.clone();
+    Box::new(move |task| match task {
+      Task::LoadTuning(path) => {
+        if let Err(e) = params.tuning.load(&path) { nih_error!("{}", e); }
+      },
+      Task::ClearTuning => { params.tuning.clear(); },
+      Task::DetectPitch => { detector.run(); },
+    })
+  }
+
   fn initialize(
     &mut self,
     _audio_io_layout: &AudioIOLayout,
@@ -263,24 +993,60 @@ impl<const NUMGRAINS: usize, const BUFSIZE: usize> Plugin for Havregryn<NUMGRAIN
     // The `reset()` function is always called right after this function. You can remove this
     // function if you do not need it.
     let sr = buffer_config.sample_rate;
-    self.imp.set_samplerate(sr);
-    self.dust.set_samplerate(sr);
+    // the post filter coefficients depend on the sample rate
+    self.post_key = (FilterMode::Off, 0.0, 0.0);
+    self.post_coeffs = Coeffs::OFF;
+    self.cloud.set_samplerate(sr);
     self.granulator.set_samplerate(sr);
     self.rate_modulator.set_samplerate(sr);
-    self.rate_random_mod.set_samplerate(sr);
+    self.lfo1.set_samplerate(sr);
+    self.lfo2.set_samplerate(sr);
+    self.random_mod.set_samplerate(sr);
+    self.follower.set_samplerate(sr);
+    self.auto_gain.set_samplerate(sr);
+    self.transient.set_samplerate(sr);
+    self.reverb.set_samplerate(sr);
+    self.shimmer.set_samplerate(sr);
+    self.detector.set_samplerate(sr);
     self.sr_recip = 1.0 / sr;
+    // also called after the state has been restored
+    self.tables.set_custom(&self.params.custom_shape.points());
+    self.custom_version = self.params.custom_shape.version();
+    self.retune(false);
     true
   }
 
   fn reset(&mut self) {
     // Reset buffers and envelopes here. This can be called from the audio thread and may not
     // allocate. You can remove this function if you do not need it.
+    self.follower.reset();
+    self.auto_gain.reset();
+    self.recording = self.granulator.recording();
+    self.capture.reset(if self.recording { 1.0 } else { 0.0 });
+    self.post_filter.iter_mut().for_each(Svf::reset);
+    self.reverb.reset();
+    self.clock.reset();
+    self.sequencer.reset();
+    self.reseed();
+    self.ratchet.reset();
+    self.cloud.reset();
+    self.transient.reset();
+    self.panner.reset();
+    self.last_step = None;
+    self.shimmer.reset();
   }
   
-  fn editor(&mut self, _async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
+  fn editor(&mut self, async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
     editor::create(
       editor::Data{
-        params: self.params.clone()
+        params: self.params.clone(),
+        view: editor::View::Grains,
+        step: 0,
+        tuning_path: String::new(),
+        tasks: Arc::new(move |task| async_executor.execute_background(task)),
+        detected: self.detector.describe(self.params.a4.value()),
+        tuning_status: self.params.tuning.status(),
+        detector: self.detector.clone(),
       },
       self.params.editor_state.clone()
     )
@@ -295,17 +1061,65 @@ impl<const NUMGRAINS: usize, const BUFSIZE: usize> Plugin for Havregryn<NUMGRAIN
 
 
     // Once per buffer
-    for (mut frame, mut aux_frame) in buffer.iter_samples().zip(aux.inputs[0].iter_samples()) {
+    let transport = context.transport();
+    let tempo = transport.tempo;
+    // song position at the start of the buffer, only followed while the host is playing
+    let song_beats = transport.pos_beats().filter(|_| transport.playing);
+    if transport.playing && !self.was_playing && self.params.lfo_reset_on_play.value() {
+      self.rate_modulator.reset();
+      self.lfo1.reset();
+      self.lfo2.reset();
+    }
+    if transport.playing && !self.was_playing && self.params.reseed_on_play.value() {
+      self.restart();
+    } else if self.params.seed.value() != self.seed {
+      self.reseed();
+    }
+    self.was_playing = transport.playing;
+
+    // re-render the custom shape after it has been edited
+    let version = self.params.custom_shape.version();
+    if version != self.custom_version {
+      if let Some(points) = self.params.custom_shape.try_points() {
+        self.tables.set_custom(&points);
+        self.custom_version = version;
+      }
+    }
+    self.retune(true);
+
+    // a finished capture is copied a chunk per buffer, then its pitch is detected on the
+    // background thread
+    if let Some(from) = self.copying {
+      self.copying = self.detector.copy(self.granulator.buffer(), from);
+      if self.copying.is_none() {
+        context.execute_background(Task::DetectPitch);
+      }
+    }
+
+    for (sample_id, (mut frame, mut aux_frame)) in buffer
+      .iter_samples()
+      .zip(aux.inputs[0].iter_samples())
+      .enumerate()
+    {
       'midi_loop: while let Some(event) = context.next_event() {
         // if event.timing() != sample_id as u32 {
         //   break;
         // }
         match event {
-          NoteEvent::NoteOn {note, ..} => { 
+          NoteEvent::NoteOn {note, velocity, ..} => { 
             self.pitches.add(note).unwrap();
+            self.held = self.count_held();
+            self.sources.velocity = velocity;
+            if self.params.rate_mod_retrigger.value() { self.rate_modulator.reset(); }
+            if self.params.lfo1.retrigger.value() { self.lfo1.reset(); }
+            if self.params.lfo2.retrigger.value() { self.lfo2.reset(); }
           },
           NoteEvent::NoteOff {note, ..} => {
             self.pitches.remove(note).unwrap();
+            self.held = self.count_held();
+          },
+          NoteEvent::MidiCC {cc: 1, value, ..} => {
+            self.sources.mod_wheel = value;
           },
           // // untested MIDI PANIC
           // NoteEvent::MidiCC { cc, .. } => {
@@ -320,19 +1134,15 @@ impl<const NUMGRAINS: usize, const BUFSIZE: usize> Plugin for Havregryn<NUMGRAIN
       if self.params.resample.value() {
         self.start_bool = true;
         self.granulator.reset_record();
+        self.copying = None;
       }
+
+      // smoothed on every sample, also while recording
+      let mix = self.params.mix.smoothed.next();
+      let gain = self.params.gain.smoothed.next();
+      let mut wet = [0.0; 2];
     
       if self.start_bool {
-        // Once per frame
-        let trig = self.params.trigger.value();
-        let position  = self.params.position.smoothed.next();
-        let duration  = self.params.duration.smoothed.next();
-        let rmod = self.params.rate_mod_amount.smoothed.next();
-        let rfrq = self.params.rate_mod_freq.smoothed.next();
-        let rate = self.params.rate.smoothed.next();
-        let jitter  = self.params.jitter.smoothed.next();
-        let pan = self.params.spread.smoothed.next();
-
         // Mono sum of input
         // since frame is already a product of an iterator, 
         // this should be fine.
@@ -341,50 +1151,293 @@ impl<const NUMGRAINS: usize, const BUFSIZE: usize> Plugin for Havregryn<NUMGRAIN
           + *frame.get_unchecked_mut(1) + *aux_frame.get_unchecked_mut(1)
           ) * 0.5
         };
+        let aux_in = unsafe { (*aux_frame.get_unchecked_mut(0) + *aux_frame.get_unchecked_mut(1)) * 0.5 };
+
+        // Modulation sources
+        let (l1, l2) = (&self.params.lfo1, &self.params.lfo2);
+        let l1frq = l1.frequency(tempo);
+        let l2frq = l2.frequency(tempo);
+        let rndfrq = self.params.random_freq.smoothed.next();
+        self.sources.lfo1 = self.lfo1.play(
+          &self.tables, l1.shape.value(), l1.morph.smoothed.next(), l1frq, l1.phase.value()
+        );
+        self.sources.lfo2 = self.lfo2.play(
+          &self.tables, l2.shape.value(), l2.morph.smoothed.next(), l2frq, l2.phase.value()
+        );
+        self.sources.random = self.random_mod.play(rndfrq.recip());
+        let env_in = match self.params.env_input.value() {
+          EnvInput::Main => unsafe { (*frame.get_unchecked_mut(0) + *frame.get_unchecked_mut(1)) * 0.5 },
+          EnvInput::Aux  => { aux_in },
+          EnvInput::Both => { mono },
+        };
+        self.follower.set_times(self.params.env_attack.value(), self.params.env_release.value());
+        self.sources.envelope = self.follower.play(env_in);
+        let m = Destinations::route(&self.params.mod_slots, &self.sources);
+
+        // Once per frame
+        let p = &self.params;
+        let sync = match p.trigger_sync.value() {
+          true  => { tempo },
+          false => { None }
+        };
+        let (division, feel) = (p.trigger_division.value(), p.trigger_feel.value());
+        let step = division.beats(feel);
+        let trig = match sync {
+          Some(tempo) => { division.seconds(feel, tempo) },
+          None        => { modulate(&p.trigger, p.trigger.value(), m.trigger) }
+        };
+        let position  = modulate(&p.position, p.position.smoothed.next(),  m.position);
+        let duration  = modulate(&p.duration, p.duration.smoothed.next(),  m.duration);
+        let rmod = p.rate_mod_depth.smoothed.next();
+        let rmorph = p.rate_mod_morph.smoothed.next();
+        let rfrq = p.rate_mod_freq.smoothed.next();
+        let rfrq = match (p.rate_mod_sync.value(), tempo) {
+          (true, Some(tempo)) => { p.rate_mod_division.value().frequency(p.rate_mod_feel.value(), tempo) },
+          _ => { rfrq }
+        };
+        // coarse is stepped, so the modulation is added to the semitones instead
+        let coarse    = (p.coarse.value() + m.rate * 96.0).clamp(-48.0, 48.0);
+        let jitter    = modulate(&p.jitter,   p.jitter.smoothed.next(),    m.jitter);
+        let pan       = modulate(&p.spread,   p.spread.smoothed.next(),    m.spread);
+        let amp = (1.0 + m.amplitude).max(0.0);
+        let filter_mode = p.filter_mode.value();
+        let cutoff = p.filter_cutoff.smoothed.next();
+        let resonance = p.filter_resonance.smoothed.next();
+        let per_grain = p.filter_per_grain.value();
+        let reverb_mix = p.reverb_mix.smoothed.next();
+        let reverb_size = p.reverb_size.smoothed.next();
+        let reverb_damping = p.reverb_damping.smoothed.next();
+        let feedback = p.shimmer_feedback.smoothed.next();
+        // every held note starts a grain on each trigger
+        let bursts = p.ratchet.value() as f32 * p.trigger_probability.value();
+        // no more grains than there are voices can overlap
+        let overlap = (duration / trig * self.held as f32 * bursts).min(NUMGRAINS as f32);
+        let amp = match self.params.auto_gain.value() {
+          true  => { amp * self.auto_gain.play(overlap) },
+          false => { amp }
+        };
         
         // granulator record buffer returns None when the buffer is full.
-        if self.granulator.record(mono).is_none() {
-          let modulator = match self.params.rate_mod_shape.value() {
-            ModShape::Sine   => { self.rate_modulator.play(&self.sin, rfrq, 0.0) },
-            ModShape::Tri    => { self.rate_modulator.play(&self.tri, rfrq, 0.0) },
-            ModShape::Saw    => { self.rate_modulator.play(&self.saw, rfrq, 0.0) },
-            ModShape::Square => { self.rate_modulator.play(&self.sqr, rfrq, 0.0) },
-            // ModShape::RANDOM => { self.rate_random_mod.play(rfrq * self.sr_recip) },
+        let capturing = self.granulator.recording();
+        let recorded = self.granulator.record(mono);
+        if capturing && !self.granulator.recording() {
+          self.copying = Some(0);
+        }
+        // the reverb keeps ringing while the buffer records, with nothing sent to it
+        let mut send = [0.0; 2];
+        if recorded.is_none() {
+          let modulator = self.rate_modulator.play(
+            &self.tables,
+            self.params.rate_mod_shape.value(),
+            rmorph,
+            rfrq,
+            self.params.rate_mod_phase.value()
+          );
+
+          // quarter notes per sample, and the song position of this sample
+          let inc = tempo.unwrap_or(120.0) / 60.0 * self.sr_recip as f64;
+          let song = song_beats.map(|beats| beats + sample_id as f64 * inc);
+          let clock = match (sync, song) {
+            (Some(_), Some(song)) => {
+              let swing = self.params.trigger_swing.value() as f64;
+              Some(self.clock.play(song, inc, step, swing))
+            },
+            _ => { None }
+          };
+
+          let density = self.params.density.value();
+          let trigger = self.cloud.play(trig, density, self.params.deviation.value());
+          // periodic triggers lock to the song position while the host is playing
+          let trigger = match (clock, density) {
+            (Some(clock), Density::Periodic) => { clock },
+            _ => { trigger }
+          };
+
+          // the sequencer replaces the other triggers, and offsets the grains of each step
+          let (trigger, step) = match self.params.seq_on.value() {
+            true  => {
+              let p = &self.params;
+              let length = p.seq_length.value() as usize;
+              let (pulses, rotate) = (p.seq_pulses.value() as usize, p.seq_rotate.value() as usize);
+              let step = self.sequencer
+                .play(song, inc, p.seq_division.value().beats(Feel::Straight), length)
+                .filter(|i| match p.seq_euclid.value() {
+                  true  => { euclidean(*i, pulses, length, rotate) },
+                  false => { p.steps[*i].on.value() }
+                })
+                .filter(|i| self.rng.gen::<f32>() < p.steps[*i].probability.value());
+              (if step.is_some() { 1.0 } else { 0.0 }, step)
+            },
+            false => { (trigger, None) }
+          };
+
+          // onsets in the aux input replace all other triggers
+          let onset = self.transient.play(
+            aux_in,
+            self.params.transient_threshold.value(),
+            self.params.transient_sensitivity.value(),
+            self.params.transient_hold.value()
+          );
+          let (trigger, step) = match self.params.transient_on.value() {
+            true  => { (onset, None) },
+            false => { (trigger, step) }
           };
 
-          let trigger = match self.params.random.value() {
-            // keep the triggers going even when unused
-            true  => { self.imp.play(trig);  self.dust.play(trig) },
-            false => { self.dust.play(trig); self.imp.play(trig)  }
+          // skip some of the triggers, and split the rest into bursts over one trigger interval
+          let interval = match self.params.seq_on.value() {
+            true  => { self.params.seq_division.value().seconds(Feel::Straight, tempo.unwrap_or(120.0)) },
+            false => { trig }
+          };
+          let probability = self.params.trigger_probability.value();
+          let trigger = match trigger >= 1.0 && self.rng.gen::<f32>() < probability {
+            true  => { self.last_step = step; 1.0 },
+            false => { 0.0 }
+          };
+          let trigger = self.ratchet.play(
+            trigger,
+            self.params.ratchet.value() as u32,
+            interval * self.sr_recip.recip()
+          );
+          let (step_position, step_semitones, step_gain) = match self.last_step {
+            Some(i) => {
+              let s = &self.params.steps[i];
+              (s.position.value(), s.rate.value(), (1.0 + s.amp.value()).max(0.0))
+            },
+            None    => { (0.0, 0.0, 1.0) }
           };
         
           if trigger >= 1.0 {
-            let pan = pan * rand::thread_rng().gen_range(-1.0..=1.0);
-            let jitter = jitter * rand::thread_rng().gen::<f32>();
+            let p = &self.params;
+            let spread = p.filter_random.value();
+            let send = p.reverb_send.value();
+            let send_random = p.reverb_send_random.value();
+            let duration_random = p.duration_random.value();
+            let (semitones, cents) = (p.rate_random_semitones.value(), p.rate_random_cents.value());
+            let amp_random = p.amp_random.value();
+            let (shape, shape_random) = (p.env_shape.value(), p.env_shape_random.value());
+            let (jitter_mode, edge) = (p.jitter_mode.value(), p.jitter_edge.value());
+            let direction = match p.direction.value() {
+              Direction::Forward => { 1.0 },
+              Direction::Reverse => { -1.0 }
+            };
+            let reverse = p.reverse.value();
+            let (pan_mode, root_key) = (p.pan_mode.value(), p.root_key.value() as f32);
+            // pitch in semitones, before the per grain detune
+            let pitch = coarse + p.fine.value() / 100.0 + rmod * modulator + step_semitones;
+            let user = p.user_scale
+              .iter()
+              .enumerate()
+              .fold(0u16, |mask, (i, note)| if note.on.value() { mask | 1 << i } else { mask });
+            let (mask, root) = (p.scale.value().mask(user), p.scale_root.value());
+            let octave_jump = p.octave_jump.value();
+            let sr = self.sr_recip.recip();
+            // every grain draws its own random values
             self.pitches.notes(&mut |note| {
-                self.granulator.trigger_new(
-                  position,
-                  duration,
+                // unmapped in the tuning
+                if self.midi_rates[note as usize] <= 0.0 { return }
+                let rng = &mut self.rng;
+                let jitter = jitter_mode.offset(jitter, rng);
+                let duration = duration * (1.0 + duration_random * rng.gen_range(-1.0..=1.0));
+                let detune = rng.gen_range(-semitones..=semitones).round()
+                  + rng.gen_range(-cents..=cents) / 100.0;
+                // the scale is on the sounding pitch, the played key plus the offsets
+                let key = note as f32;
+                let pitch = quantize(key + pitch + detune, mask, root) - key;
+                let pitch = match rng.gen::<f32>() < octave_jump {
+                  true  => { pitch + if rng.gen::<bool>() { 12.0 } else { -12.0 } },
+                  false => { pitch }
+                };
+                let direction = match rng.gen::<f32>() < reverse {
+                  true  => { -direction },
+                  false => { direction }
+                };
+                let pan = self.panner.pan(
+                  pan_mode,
                   pan,
-                  rate * self.midi_rates[note as usize] + (rmod * modulator),
-                  jitter
+                  note as f32 - root_key + pitch,
+                  position + step_position + jitter,
+                  rng
                 );
+                let filter = match per_grain {
+                  true  => {
+                    let cutoff = cutoff * rng.gen_range(-spread..=spread).exp2();
+                    Coeffs::new(filter_mode, cutoff, resonance, sr)
+                  },
+                  false => { Coeffs::OFF }
+                };
+                self.granulator.trigger_new(GrainParams {
+                  position: position + step_position,
+                  duration,
+                  pan,
+                  rate: direction * self.midi_rates[note as usize] * (pitch / 12.0).exp2(),
+                  jitter,
+                  edge,
+                  gain: step_gain * (1.0 - amp_random * rng.gen::<f32>()),
+                  shape: shape + shape_random * 0.5 * rng.gen_range(-1.0..=1.0),
+                  filter,
+                  send: send + send_random * rng.gen_range(-1.0..=1.0),
+                });
               }
             )
           }
 
-          let out_frame = self.granulator.play::<Linear, Linear>();
-
-          frame
-            .into_iter()
-            .zip(out_frame.iter())
-            .for_each(
-              |(sample, grain)| { 
-              *sample = *grain 
-            }
+          self.panner.tick(self.sr_recip);
+          let mut out_frame = self.granulator.play();
+          out_frame.main = pan::width(out_frame.main, self.params.width.smoothed.next());
+          // only recalculated while the cutoff or resonance is moving
+          if (filter_mode, cutoff, resonance) != self.post_key {
+            self.post_key = (filter_mode, cutoff, resonance);
+            self.post_coeffs = Coeffs::new(filter_mode, cutoff, resonance, self.sr_recip.recip());
+          }
+          let post = match per_grain {
+            true  => { Coeffs::OFF },
+            false => { self.post_coeffs }
+          };
+          // pitched grains are written back into the buffer
+          let shimmer = self.shimmer.process(
+            (out_frame.main[0] + out_frame.main[1]) * 0.5,
+            shimmer::ratio(self.params.shimmer_interval.value())
           );
+          if feedback > 0.0 {
+            self.granulator.overdub(soft_clip(shimmer), feedback);
+          }
+
+          send = out_frame.send;
+          out_frame.main
+            .iter()
+            .zip(wet.iter_mut())
+            .zip(self.post_filter.iter_mut())
+            .for_each(|((grain, w), filter)| {
+              *w = filter.process(*grain, &post) * amp
+            });
         }
+
+        // the tail is added after the post filter and amp
+        let tail = self.reverb.process(send, reverb_size, reverb_damping);
+        wet.iter_mut().zip(tail.iter()).for_each(|(w, tail)| *w += *tail * reverb_mix);
+      }
+
+      // only the grains are clipped, the dry signal is left alone
+      if self.params.soft_clip.value() {
+        wet.iter_mut().for_each(|w| *w = soft_clip(*w));
       }
+
+      // the grains are silent while the buffer is recording, so the dry signal is
+      // passed through at unity, whatever the mix
+      let recording = self.granulator.recording();
+      if recording != self.recording {
+        self.recording = recording;
+        self.capture.set_target(self.sr_recip.recip(), if recording { 1.0 } else { 0.0 });
+      }
+      let dry = 1.0 - mix * (1.0 - self.capture.next());
+      frame
+        .into_iter()
+        .zip(wet.iter())
+        .for_each(
+          |(sample, grain)| { 
+          *sample = (*sample * dry + *grain * mix) * gain
+        }
+      );
     }
     ProcessStatus::Normal
   }
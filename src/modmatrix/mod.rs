@@ -0,0 +1,133 @@
+use std::sync::Arc;
+use nih_plug::prelude::*;
+
+/// Number of routings in the modulation matrix.
+pub const SLOTS: usize = 6;
+
+#[derive(Enum, PartialEq, Clone, Copy)]
+pub enum ModSource {
+  #[name = "-"]
+  None,
+  #[name = "LFO 1"]
+  Lfo1,
+  #[name = "LFO 2"]
+  Lfo2,
+  Random,
+  #[name = "Env"]
+  Envelope,
+  Velocity,
+  #[name = "Mod wheel"]
+  ModWheel,
+}
+
+#[derive(Enum, PartialEq, Clone, Copy)]
+pub enum ModDest {
+  #[name = "-"]
+  None,
+  Position,
+  Duration,
+  Jitter,
+  Spread,
+  Trigger,
+  #[name = "Amp"]
+  Amplitude,
+  Rate,
+}
+
+/// A single routing, from one source to one destination.
+#[derive(Params)]
+pub struct ModSlotParams {
+  #[id = "mod-source"]
+  pub source: EnumParam<ModSource>,
+  #[id = "mod-dest"]
+  pub dest: EnumParam<ModDest>,
+  #[id = "mod-depth"]
+  pub depth: FloatParam,
+}
+
+impl ModSlotParams {
+  pub fn new(slot: usize) -> Self {
+    Self {
+      source: EnumParam::new(format!("slot {} source", slot), ModSource::None),
+      dest: EnumParam::new(format!("slot {} dest", slot), ModDest::None),
+      depth: FloatParam::new(
+        format!("slot {} depth", slot),
+        0.0,
+        FloatRange::Linear { min: -1.0, max: 1.0 }
+      )
+        .with_smoother(SmoothingStyle::Linear(20.0))
+        .with_value_to_string(Arc::new(|i| { format!("{:.2}", i) })),
+    }
+  }
+}
+
+/// The current value of every modulation source.
+/// LFOs and random are bipolar, the rest are in the range `(0.0, 1.0)`.
+#[derive(Default)]
+pub struct Sources {
+  pub lfo1: f32,
+  pub lfo2: f32,
+  pub random: f32,
+  pub envelope: f32,
+  pub velocity: f32,
+  pub mod_wheel: f32,
+}
+
+impl Sources {
+  #[inline]
+  pub fn get(&self, source: ModSource) -> f32 {
+    match source {
+      ModSource::None     => { 0.0 },
+      ModSource::Lfo1     => { self.lfo1 },
+      ModSource::Lfo2     => { self.lfo2 },
+      ModSource::Random   => { self.random },
+      ModSource::Envelope => { self.envelope },
+      ModSource::Velocity => { self.velocity },
+      ModSource::ModWheel => { self.mod_wheel },
+    }
+  }
+}
+
+/// Summed modulation for every destination.
+#[derive(Default)]
+pub struct Destinations {
+  pub position: f32,
+  pub duration: f32,
+  pub jitter: f32,
+  pub spread: f32,
+  pub trigger: f32,
+  pub amplitude: f32,
+  pub rate: f32,
+}
+
+impl Destinations {
+  /// Sums `source * depth` of every slot into its destination.
+  #[inline]
+  pub fn route(slots: &[ModSlotParams; SLOTS], sources: &Sources) -> Self {
+    let mut dest = Self::default();
+    for slot in slots.iter() {
+      // advance the smoother even when the slot is unused
+      let depth = slot.depth.smoothed.next();
+      let amount = sources.get(slot.source.value()) * depth;
+      match slot.dest.value() {
+        ModDest::None      => {},
+        ModDest::Position  => { dest.position  += amount },
+        ModDest::Duration  => { dest.duration  += amount },
+        ModDest::Jitter    => { dest.jitter    += amount },
+        ModDest::Spread    => { dest.spread    += amount },
+        ModDest::Trigger   => { dest.trigger   += amount },
+        ModDest::Amplitude => { dest.amplitude += amount },
+        ModDest::Rate      => { dest.rate      += amount },
+      }
+    }
+    dest
+  }
+}
+
+/// Offsets `value` by `amount` in the parameter's normalized range,
+/// a depth of 1.0 sweeps the whole range of the parameter.
+#[inline]
+pub fn modulate(param: &FloatParam, value: f32, amount: f32) -> f32 {
+  if amount == 0.0 { return value }
+  param.preview_plain((param.preview_normalized(value) + amount).clamp(0.0, 1.0))
+}
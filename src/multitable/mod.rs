@@ -1,4 +1,104 @@
-use rust_dsp::interpolation::{Interpolation, Linear};
+use std::f64::consts::TAU;
+use crate::SIZE;
+use crate::custom::{Point, render};
+
+/// Number of shapes to morph between: sine, tri, saw and square.
+pub const SHAPES: usize = 4;
+/// Number of band-limited versions of every shape, level `n` holds `2^n` harmonics.
+pub const LEVELS: usize = 10;
+
+/// Band-limited wavetables, one set of mip levels per shape.
+/// Built once, outside of the audio thread, and shared between all `MultiTable`s.
+/// Also holds the user drawn custom shape, which is not band-limited.
+pub struct Wavetables {
+  tables: Vec<f32>,
+  custom: Vec<f32>,
+}
+
+impl Wavetables {
+  pub fn new() -> Self {
+    let sine: Vec<f64> = (0..SIZE).map(|i| (TAU * i as f64 / SIZE as f64).sin()).collect();
+    let mut tables = vec![0.0; SHAPES * LEVELS * SIZE];
+    let mut acc = vec![0.0f64; SIZE];
+
+    for shape in 0..SHAPES {
+      acc.fill(0.0);
+      let mut harmonic = 1;
+      for level in 0..LEVELS {
+        // every level adds the harmonics up to 2^level to the one below
+        while harmonic <= 1 << level {
+          let amp = Self::amplitude(shape, harmonic);
+          if amp != 0.0 {
+            // sin(k * x) read from the fundamental, since x is a multiple of TAU / SIZE
+            acc.iter_mut().enumerate().for_each(|(i, a)| {
+              *a += amp * sine[(harmonic * i) % SIZE];
+            });
+          }
+          harmonic += 1;
+        }
+        let peak = acc.iter().fold(0.0f64, |m, a| m.max(a.abs()));
+        let offset = (shape * LEVELS + level) * SIZE;
+        tables[offset..offset + SIZE]
+          .iter_mut()
+          .zip(acc.iter())
+          .for_each(|(t, a)| *t = (a / peak) as f32);
+      }
+    }
+    Self { tables, custom: vec![0.0; SIZE] }
+  }
+
+  /// Renders the breakpoints of the custom shape, does not allocate.
+  #[inline]
+  pub fn set_custom(&mut self, points: &[Point]) {
+    render(points, &mut self.custom);
+  }
+
+  /// Fourier series amplitude of harmonic `k` for each shape.
+  #[inline]
+  fn amplitude(shape: usize, k: usize) -> f64 {
+    let odd = k % 2 == 1;
+    let kf = k as f64;
+    match shape {
+      // sine
+      0 => { if k == 1 { 1.0 } else { 0.0 } },
+      // triangle, odd harmonics with alternating sign, falling off with 1/k^2
+      1 => {
+        if !odd { return 0.0 }
+        let sign = if (k / 2) % 2 == 0 { 1.0 } else { -1.0 };
+        sign / (kf * kf)
+      },
+      // sawtooth, every harmonic with alternating sign, falling off with 1/k
+      2 => { if odd { 1.0 / kf } else { -1.0 / kf } },
+      // square, odd harmonics falling off with 1/k
+      _ => { if odd { 1.0 / kf } else { 0.0 } },
+    }
+  }
+
+  /// Highest mip level that does not alias at `frequency`.
+  #[inline]
+  pub fn level(frequency: f32, nyquist: f32) -> usize {
+    if frequency <= 0.0 { return LEVELS - 1 }
+    let harmonics = nyquist / frequency;
+    if harmonics < 2.0 { return 0 }
+    (harmonics.log2() as usize).min(LEVELS - 1)
+  }
+
+  #[inline]
+  fn read(&self, shape: usize, level: usize, position: f32) -> f32 {
+    Self::interpolate(&self.tables[(shape * LEVELS + level) * SIZE..][..SIZE], position)
+  }
+
+  #[inline]
+  fn interpolate(table: &[f32], position: f32) -> f32 {
+    let i = position as usize;
+    let frac = position - i as f32;
+    let a = table[i % SIZE];
+    let b = table[(i + 1) % SIZE];
+    a + (b - a) * frac
+  }
+}
+
+/// Morphing wavetable oscillator.
 pub struct MultiTable {
   position: f32,
   samplerate: f32,
@@ -14,17 +114,45 @@ impl MultiTable {
     }
   }
 
+  /// `morph` crossfades continuously between sine `0.0`, tri `1.0`, saw `2.0` and square `3.0`.
+  /// `frequency` may be negative, which plays the table backwards, or pass through zero.
+  /// `phase` is an offset, in the range `(0.0, 1.0)`, from the running position in the table.
+  #[inline]
+  pub fn play(&mut self, tables: &Wavetables, morph: f32, frequency: f32, phase: f32) -> f32 {
+    let nyquist = self.samplerate * 0.5;
+    if frequency.abs() >= nyquist { return 0.0 }
+    let position = self.advance(frequency, phase);
+
+    let level = Wavetables::level(frequency.abs(), nyquist);
+    let morph = morph.clamp(0.0, (SHAPES - 1) as f32);
+    let shape = (morph as usize).min(SHAPES - 2);
+    let frac = morph - shape as f32;
+
+    let a = tables.read(shape, level, position);
+    let b = tables.read(shape + 1, level, position);
+    a + (b - a) * frac
+  }
 
+  /// Plays the custom shape.
   #[inline]
-  pub fn play<const N: usize>(&mut self, table: &[f32; N], frequency: f32, phase: f32) -> f32 {
-    if frequency > self.samplerate * 0.5 { return 0.0 }
-    let len = N as f32;
+  pub fn play_custom(&mut self, tables: &Wavetables, frequency: f32, phase: f32) -> f32 {
+    if frequency.abs() >= self.samplerate * 0.5 { return 0.0 }
+    let position = self.advance(frequency, phase);
+    Wavetables::interpolate(&tables.custom, position)
+  }
 
-    self.position += (len * self.sr_recip * frequency) + ( phase * len );
-    while self.position > len {
-      self.position -= len;
-    }
-    Linear::interpolate(self.position, table, N)
+  /// Moves the running position one sample, returns the read position including `phase`.
+  #[inline]
+  fn advance(&mut self, frequency: f32, phase: f32) -> f32 {
+    let len = SIZE as f32;
+    self.position = (self.position + len * self.sr_recip * frequency).rem_euclid(len);
+    (self.position + (phase * len)).rem_euclid(len)
+  }
+
+  /// Restarts the table from the beginning.
+  #[inline]
+  pub fn reset(&mut self) {
+    self.position = 0.0;
   }
 
   #[inline]
@@ -33,3 +161,109 @@ impl MultiTable {
     self.samplerate = samplerate;
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::Rng;
+  use crate::random::Prng;
+
+  const SR: f32 = 48000.0;
+
+  fn osc() -> MultiTable {
+    let mut osc = MultiTable::new();
+    osc.set_samplerate(SR);
+    osc
+  }
+
+  #[test]
+  fn bounded_for_any_input() {
+    let tables = Wavetables::new();
+    let mut rng = Prng::new(29);
+    for _ in 0..64 {
+      let mut osc = osc();
+      let morph = rng.gen_range(-1.0..4.0);
+      let freq = rng.gen_range(-SR * 0.5..SR * 0.5);
+      let phase = rng.gen_range(0.0..1.0);
+      for _ in 0..256 {
+        let out = osc.play(&tables, morph, freq, phase);
+        assert!(out.is_finite());
+        assert!(out.abs() <= 1.0 + 1e-4, "{out} at morph {morph}, freq {freq}");
+        assert!((0.0..=SIZE as f32).contains(&osc.position));
+      }
+    }
+  }
+
+  #[test]
+  fn negative_frequency_plays_backwards() {
+    let tables = Wavetables::new();
+    let mut rng = Prng::new(29);
+    for _ in 0..16 {
+      let freq = rng.gen_range(0.1..1000.0);
+      let (mut fwd, mut bwd) = (osc(), osc());
+      for _ in 0..1024 {
+        // sine is odd, so reading it backwards mirrors the output
+        let a = fwd.play(&tables, 0.0, freq, 0.0);
+        let b = bwd.play(&tables, 0.0, -freq, 0.0);
+        assert!((a + b).abs() < 1e-3);
+      }
+    }
+  }
+
+  #[test]
+  fn through_zero_is_continuous() {
+    let tables = Wavetables::new();
+    let mut osc = osc();
+    let steps = 4800;
+    let mut prev = osc.play(&tables, 1.0, 20.0, 0.0);
+    for n in 0..steps {
+      // sweep from 20 Hz to -20 Hz
+      let freq = 20.0 - 40.0 * n as f32 / steps as f32;
+      let out = osc.play(&tables, 1.0, freq, 0.0);
+      // a triangle at 20 Hz never moves more than 4 * 20 / SR per sample
+      assert!((out - prev).abs() < 4.0 * 20.0 / SR + 1e-3);
+      prev = out;
+    }
+  }
+
+  #[test]
+  fn morph_is_continuous() {
+    let tables = Wavetables::new();
+    let mut rng = Prng::new(29);
+    for _ in 0..64 {
+      let morph: f32 = rng.gen_range(0.0..2.99);
+      let freq = rng.gen_range(0.1..SR * 0.5);
+      let phase = rng.gen_range(0.0..1.0);
+      // two oscillators in step, a small change of morph only moves the output a little
+      let (mut a, mut b) = (osc(), osc());
+      for _ in 0..64 {
+        let x = a.play(&tables, morph, freq, phase);
+        let y = b.play(&tables, morph + 0.01, freq, phase);
+        assert!((x - y).abs() <= 0.02 + 1e-4, "{x} {y} at morph {morph}, freq {freq}");
+      }
+    }
+  }
+
+  #[test]
+  fn level_is_band_limited() {
+    let mut rng = Prng::new(29);
+    let nyquist = SR * 0.5;
+    for _ in 0..1024 {
+      let freq = rng.gen_range(0.01..nyquist);
+      let level = Wavetables::level(freq, nyquist);
+      assert!(level == 0 || (1 << level) as f32 * freq <= nyquist);
+      assert!(level == LEVELS - 1 || (1 << (level + 1)) as f32 * freq > nyquist);
+    }
+  }
+
+  #[test]
+  fn lowest_level_is_sine() {
+    let tables = Wavetables::new();
+    for shape in 0..SHAPES {
+      for i in (0..SIZE).step_by(64) {
+        let sine = (TAU * i as f64 / SIZE as f64).sin() as f32;
+        assert!((tables.read(shape, 0, i as f32) - sine).abs() < 1e-4);
+      }
+    }
+  }
+}
@@ -0,0 +1,107 @@
+use std::f32::consts::TAU;
+use nih_plug::prelude::*;
+use rand::Rng;
+
+/// Frequency of the pan sweep, in Hz.
+const SWEEP: f32 = 0.1;
+/// Semitones from the root key panned fully to one side.
+const PITCH_RANGE: f32 = 24.0;
+
+/// How the grains are spread over the stereo field.
+#[derive(Enum, PartialEq, Clone, Copy)]
+pub enum PanMode {
+  Random,
+  #[name = "Alternating"]
+  Alternate,
+  Sweep,
+  /// Low notes to the left, high notes to the right.
+  #[name = "By pitch"]
+  Pitch,
+  /// The start of the buffer to the left, the end to the right.
+  #[name = "By position"]
+  Position,
+}
+
+/// Places new grains in the stereo field.
+pub struct Panner {
+  side: f32,
+  phase: f32,
+}
+
+impl Panner {
+  pub fn new() -> Self {
+    Self { side: 1.0, phase: 0.0 }
+  }
+
+  /// Moves the sweep forward by one sample.
+  #[inline]
+  pub fn tick(&mut self, sr_recip: f32) {
+    self.phase = (self.phase + SWEEP * sr_recip).fract();
+  }
+
+  /// Pan of a new grain, scaled by `spread`. `semitones` is the pitch of the grain
+  /// from the root key, `position` its start relative to the buffer length.
+  #[inline]
+  pub fn pan(
+    &mut self,
+    mode: PanMode,
+    spread: f32,
+    semitones: f32,
+    position: f32,
+    rng: &mut impl Rng
+  ) -> f32 {
+    let pan = match mode {
+      PanMode::Random    => { rng.gen_range(-1.0..=1.0) },
+      PanMode::Alternate => { self.side = -self.side; self.side },
+      PanMode::Sweep     => { (TAU * self.phase).sin() },
+      PanMode::Pitch     => { (semitones / PITCH_RANGE).clamp(-1.0, 1.0) },
+      PanMode::Position  => { position.rem_euclid(1.0) * 2.0 - 1.0 },
+    };
+    pan * spread
+  }
+
+  #[inline]
+  pub fn reset(&mut self) {
+    self.side = 1.0;
+    self.phase = 0.0;
+  }
+}
+
+/// Mid/side width of a stereo frame, `0.0` is mono and `1.0` leaves it alone.
+#[inline]
+pub fn width(frame: [f32; 2], width: f32) -> [f32; 2] {
+  let mid = (frame[0] + frame[1]) * 0.5;
+  let side = (frame[0] - frame[1]) * 0.5 * width;
+  [mid + side, mid - side]
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn modes() {
+    let mut rng = crate::random::Prng::new(1);
+    let mut panner = Panner::new();
+    assert_eq!(panner.pan(PanMode::Alternate, 0.5, 0.0, 0.0, &mut rng), -0.5);
+    assert_eq!(panner.pan(PanMode::Alternate, 0.5, 0.0, 0.0, &mut rng), 0.5);
+    assert_eq!(panner.pan(PanMode::Pitch, 1.0, -12.0, 0.0, &mut rng), -0.5);
+    assert_eq!(panner.pan(PanMode::Pitch, 1.0, 36.0, 0.0, &mut rng), 1.0);
+    assert_eq!(panner.pan(PanMode::Position, 1.0, 0.0, 0.75, &mut rng), 0.5);
+    assert_eq!(panner.pan(PanMode::Position, 1.0, 0.0, -0.25, &mut rng), 0.5);
+    assert_eq!(panner.pan(PanMode::Sweep, 1.0, 0.0, 0.0, &mut rng), 0.0);
+    // a quarter of the way through the sweep
+    for _ in 0..25 { panner.tick(0.1); }
+    assert!((panner.pan(PanMode::Sweep, 1.0, 0.0, 0.0, &mut rng) - 1.0).abs() < 1e-4);
+    for _ in 0..100 {
+      assert!(panner.pan(PanMode::Random, 0.3, 0.0, 0.0, &mut rng).abs() <= 0.3);
+    }
+  }
+
+  #[test]
+  fn narrows_to_mono() {
+    assert_eq!(width([1.0, 0.0], 0.0), [0.5, 0.5]);
+    assert_eq!(width([1.0, 0.0], 1.0), [1.0, 0.0]);
+    assert_eq!(width([1.0, 0.0], 2.0), [1.5, -0.5]);
+  }
+}
@@ -1,46 +1,150 @@
-use rand::{self, Rng};
+use rand::{Rng, RngCore};
 use rust_dsp::trig::Trigger;
 
+/// Seedable, allocation free pseudo random number generator (splitmix64).
+///
+/// Implements `RngCore`, so every `rand::Rng` method works on it.
+/// The same seed always gives the same sequence.
+#[derive(Clone)]
+pub struct Prng {
+  state: u64,
+}
+
+impl Prng {
+  pub const fn new(seed: u64) -> Self {
+    Self { state: seed }
+  }
+
+  #[inline]
+  pub fn reseed(&mut self, seed: u64) {
+    self.state = seed;
+  }
+}
+
+impl RngCore for Prng {
+  #[inline]
+  fn next_u32(&mut self) -> u32 {
+    (self.next_u64() >> 32) as u32
+  }
+
+  #[inline]
+  fn next_u64(&mut self) -> u64 {
+    self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = self.state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+  }
+
+  fn fill_bytes(&mut self, dest: &mut [u8]) {
+    for chunk in dest.chunks_mut(8) {
+      let bytes = self.next_u64().to_le_bytes();
+      chunk.copy_from_slice(&bytes[..chunk.len()]);
+    }
+  }
+
+  fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+    self.fill_bytes(dest);
+    Ok(())
+  }
+}
+
+/// Random modulation source.
+///
+/// Picks a new random target in the range `(-1.0, 1.0)` once every `duration` seconds.
+/// `play` glides linearly towards the target (smooth random), `hold` jumps to it and
+/// holds it for the whole period (sample-and-hold) and `noise` returns raw white noise.
 pub struct Random {
-  next: f32,
-  duration: f32,
-  duration_in_samples: u64,
-  counter: u64,
-  samplerate: f32,
+  target: f32,
   current: f32,
   inc: f32,
-  sr_recip: f32,
+  counter: u64,
+  duration_in_samples: u64,
+  samplerate: f32,
+  rng: Prng,
+}
+
+impl Random {
+  /// Moves the internal counter one sample forward, and draws a new target value
+  /// when the current period has passed.
+  #[inline]
+  fn advance(&mut self, duration: f32) {
+    if self.counter >= self.duration_in_samples {
+      // Never let the period collapse to zero samples, that would stall the counter.
+      self.duration_in_samples = ((self.samplerate * duration) as u64).max(1);
+      self.counter = 0;
+      self.target = self.rng.gen_range(-1.0..=1.0);
+      self.inc = (self.target - self.current) / self.duration_in_samples as f32;
+    }
+    self.counter += 1;
+  }
+
+  /// Sample-and-hold, a new random value every `duration` seconds.
+  #[inline]
+  pub fn hold(&mut self, duration: f32) -> f32 {
+    self.advance(duration);
+    // keep the smooth output in sync, so that switching shapes does not jump
+    self.current += self.inc;
+    self.target
+  }
+
+  /// Forces a new random value on the next sample.
+  #[inline]
+  pub fn reset(&mut self) {
+    self.counter = self.duration_in_samples;
+  }
+
+  /// Raw white noise, a new random value every sample.
+  #[inline]
+  pub fn noise(&mut self) -> f32 {
+    self.rng.gen_range(-1.0..=1.0)
+  }
+
+  #[inline]
+  pub fn reseed(&mut self, seed: u64) {
+    self.rng.reseed(seed);
+  }
+
+  /// Back to the state it was created in, without touching the generator.
+  #[inline]
+  pub fn restart(&mut self) {
+    self.target = 0.0;
+    self.current = 0.0;
+    self.inc = 0.0;
+    self.counter = 0;
+    self.duration_in_samples = 0;
+  }
+}
+
+/// Normally distributed value with a standard deviation of `1.0`, box-muller.
+#[inline]
+pub fn gaussian(rng: &mut impl Rng) -> f32 {
+  let (u1, u2) = (1.0 - rng.gen::<f32>(), rng.gen::<f32>());
+  (-2.0 * u1.ln()).sqrt() * (2.0 * std::f32::consts::PI * u2).cos()
 }
 
 impl Trigger for Random {
   fn new(samplerate: f32) -> Self {
     Self {
-      sr_recip: 1.0/ samplerate,
       samplerate,
-      next: 0.0,
+      target: 0.0,
       current: 0.0,
       inc: 0.0,
       counter: 0,
-      duration: 0.0,
       duration_in_samples: 0,
+      rng: Prng::new(0),
     }
   }
 
+  /// Smooth random, linearly interpolates between random values,
+  /// reaching each new value after `duration` seconds.
   fn play(&mut self, duration: f32) -> f32 {
-    self.counter += 1;
-    if self.counter >= (self.duration * self.samplerate) as u64 {
-      self.duration = duration;
-      self.duration_in_samples = (self.samplerate * duration) as u64;
-      self.counter = 0;
-      self.next = (rand::thread_rng().gen::<f32>() * 2.0) - 1.0;
-      self.inc = ( self.next - self.current ) / self.duration_in_samples as f32;
-    }
+    self.advance(duration);
     self.current += self.inc;
     self.current
   }
 
   fn set_samplerate(&mut self, samplerate: f32) {
-      self.sr_recip = 1.0 / samplerate;
       self.samplerate = samplerate;
   }
 }
@@ -48,11 +152,94 @@ impl Trigger for Random {
 #[cfg(test)]
 mod tests {
   use super::*;
-  
+
+  const SR: f32 = 48000.0;
+
   #[test]
-  fn poll() {
-    let _rnd = Random::new(48000.0);
+  fn smooth_reaches_target() {
+    let mut rnd = Random::new(SR);
+    let duration = 0.01;
+    let samples = (SR * duration) as usize;
+    let mut out = 0.0;
+    for _ in 0..samples { out = rnd.play(duration); }
+    assert!((out - rnd.target).abs() < 1e-4);
+  }
 
-    
+  #[test]
+  fn smooth_is_continuous() {
+    let mut rnd = Random::new(SR);
+    let duration = 0.01;
+    // largest possible step is a full swing across one period
+    let max_step = 2.0 / (SR * duration) + 1e-5;
+    let mut prev = 0.0;
+    for _ in 0..(SR as usize) {
+      let out = rnd.play(duration);
+      assert!((out - prev).abs() <= max_step);
+      assert!((-1.0..=1.0).contains(&out));
+      prev = out;
+    }
+  }
+
+  #[test]
+  fn hold_is_constant_over_period() {
+    let mut rnd = Random::new(SR);
+    let duration = 0.01;
+    let samples = (SR * duration) as usize;
+    for _ in 0..4 {
+      let first = rnd.hold(duration);
+      for _ in 1..samples {
+        assert_eq!(rnd.hold(duration), first);
+      }
+    }
+  }
+
+  #[test]
+  fn zero_duration_does_not_stall() {
+    let mut rnd = Random::new(SR);
+    for _ in 0..16 { rnd.hold(0.0); }
+    assert_eq!(rnd.duration_in_samples, 1);
+    assert_eq!(rnd.counter, 1);
+  }
+
+  #[test]
+  fn gaussian_spread() {
+    let mut rng = Prng::new(1);
+    let values: Vec<f32> = (0..100_000).map(|_| gaussian(&mut rng)).collect();
+    let mean = values.iter().sum::<f32>() / values.len() as f32;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / values.len() as f32;
+    assert!(mean.abs() < 0.02);
+    assert!((variance - 1.0).abs() < 0.05);
+  }
+
+  #[test]
+  fn same_seed_same_sequence() {
+    let (mut a, mut b) = (Prng::new(42), Prng::new(42));
+    for _ in 0..1000 { assert_eq!(a.next_u64(), b.next_u64()); }
+    let mut c = Prng::new(43);
+    assert_ne!(a.next_u64(), c.next_u64());
+    // and the modulation source follows its seed
+    let (mut a, mut b) = (Random::new(SR), Random::new(SR));
+    a.reseed(7);
+    b.reseed(7);
+    for _ in 0..1000 { assert_eq!(a.noise(), b.noise()); }
+  }
+
+  #[test]
+  fn restart_repeats_from_seed() {
+    let mut rnd = Random::new(SR);
+    rnd.reseed(9);
+    let first = (0..10000).map(|_| rnd.play(0.01)).collect::<Vec<f32>>();
+    (0..777).for_each(|_| { rnd.play(0.01); });
+    rnd.reseed(9);
+    rnd.restart();
+    assert!((0..10000).map(|_| rnd.play(0.01)).eq(first.into_iter()));
+  }
+
+  #[test]
+  fn noise_in_range() {
+    let mut rnd = Random::new(SR);
+    for _ in 0..1024 {
+      assert!((-1.0..=1.0).contains(&rnd.noise()));
+    }
   }
 }
@@ -0,0 +1,60 @@
+/// Splits every trigger into a burst of evenly spaced triggers.
+pub struct Ratchet {
+  remaining: u32,
+  counter: u32,
+  spacing: u32,
+}
+
+impl Ratchet {
+  pub fn new() -> Self {
+    Self { remaining: 0, counter: 0, spacing: 1 }
+  }
+
+  /// Passes `trigger` through, and follows it with `count - 1` more triggers spread
+  /// over the next `length` samples. A new trigger cuts a running burst short.
+  #[inline]
+  pub fn play(&mut self, trigger: f32, count: u32, length: f32) -> f32 {
+    if trigger >= 1.0 {
+      self.remaining = count.saturating_sub(1);
+      self.spacing = ((length / count.max(1) as f32) as u32).max(1);
+      self.counter = self.spacing;
+      return 1.0
+    }
+    if self.remaining == 0 { return 0.0 }
+    self.counter -= 1;
+    if self.counter > 0 { return 0.0 }
+    self.remaining -= 1;
+    self.counter = self.spacing;
+    1.0
+  }
+
+  #[inline]
+  pub fn reset(&mut self) {
+    self.remaining = 0;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn burst(ratchet: &mut Ratchet, count: u32, length: f32, samples: usize) -> Vec<usize> {
+    (0..samples)
+      .filter(|n| ratchet.play(if *n == 0 { 1.0 } else { 0.0 }, count, length) >= 1.0)
+      .collect()
+  }
+
+  #[test]
+  fn evenly_spaced() {
+    let mut r = Ratchet::new();
+    assert_eq!(burst(&mut r, 4, 100.0, 200), vec![0, 25, 50, 75]);
+    assert_eq!(burst(&mut r, 1, 100.0, 200), vec![0]);
+  }
+
+  #[test]
+  fn new_trigger_cuts_burst() {
+    let mut r = Ratchet::new();
+    burst(&mut r, 4, 100.0, 30);
+    assert_eq!(burst(&mut r, 2, 10.0, 100), vec![0, 5]);
+  }
+}
@@ -0,0 +1,239 @@
+//! Stereo feedback delay network reverb.
+//!
+//! Fully deterministic, there is no randomness and no modulation in the delay lines,
+//! so the same input always gives the same tail.
+
+/// Number of delay lines in the feedback network.
+const LINES: usize = 8;
+/// Delay line lengths in seconds, at `size` 0.5.
+const LENGTHS: [f32; LINES] = [0.0297, 0.0371, 0.0411, 0.0437, 0.0533, 0.0599, 0.0671, 0.0733];
+/// Allpass diffuser lengths in seconds, per channel.
+const DIFFUSERS: [[f32; 4]; 2] = [
+  [0.0047, 0.0036, 0.0127, 0.0093],
+  [0.0051, 0.0033, 0.0119, 0.0101],
+];
+const DIFFUSION: f32 = 0.6;
+/// Longest line, relative to `LENGTHS`.
+const MAX_SCALE: f32 = 1.5;
+
+struct Delay {
+  buffer: Vec<f32>,
+  write: usize,
+}
+
+impl Delay {
+  fn new(len: usize) -> Self {
+    Self { buffer: vec![0.0; len.max(1)], write: 0 }
+  }
+
+  /// Sample written `delay` samples ago.
+  #[inline]
+  fn read(&self, delay: usize) -> f32 {
+    let len = self.buffer.len();
+    self.buffer[(self.write + len - delay.clamp(1, len)) % len]
+  }
+
+  #[inline]
+  fn write(&mut self, sample: f32) {
+    self.buffer[self.write] = sample;
+    self.write = (self.write + 1) % self.buffer.len();
+  }
+
+  fn clear(&mut self) {
+    self.buffer.fill(0.0);
+  }
+}
+
+struct Allpass {
+  delay: Delay,
+  len: usize,
+}
+
+impl Allpass {
+  #[inline]
+  fn process(&mut self, input: f32) -> f32 {
+    let delayed = self.delay.read(self.len);
+    let v = input + DIFFUSION * delayed;
+    self.delay.write(v);
+    delayed - DIFFUSION * v
+  }
+}
+
+pub struct Reverb {
+  lines: Vec<Delay>,
+  lengths: [usize; LINES],
+  gains: [f32; LINES],
+  damp: [f32; LINES],
+  diffusers: Vec<Allpass>,
+  size: f32,
+  samplerate: f32,
+}
+
+impl Reverb {
+  pub fn new() -> Self {
+    Self {
+      lines: Vec::new(),
+      lengths: [1; LINES],
+      gains: [0.0; LINES],
+      damp: [0.0; LINES],
+      diffusers: Vec::new(),
+      size: -1.0,
+      samplerate: 0.0,
+    }
+  }
+
+  /// Allocates the delay lines, call outside of the audio thread.
+  pub fn set_samplerate(&mut self, samplerate: f32) {
+    self.samplerate = samplerate;
+    self.lines = LENGTHS
+      .iter()
+      .map(|l| Delay::new((l * MAX_SCALE * samplerate) as usize + 1))
+      .collect();
+    self.diffusers = DIFFUSERS
+      .iter()
+      .flatten()
+      .map(|l| {
+        let len = (l * samplerate) as usize;
+        Allpass { delay: Delay::new(len + 1), len: len.max(1) }
+      })
+      .collect();
+    self.damp = [0.0; LINES];
+    // force the lengths to be recalculated
+    self.size = -1.0;
+  }
+
+  /// `size` in the range `(0.0, 1.0)` sets both the room size and the decay time.
+  #[inline]
+  fn set_size(&mut self, size: f32) {
+    if size == self.size { return }
+    self.size = size;
+    let scale = 0.5 + size;
+    // decay time from half a second up to ten seconds
+    let rt60 = 0.5 + 9.5 * size * size;
+    let sr = self.samplerate;
+    self.lengths
+      .iter_mut()
+      .zip(self.gains.iter_mut())
+      .zip(LENGTHS)
+      .for_each(|((len, gain), base)| {
+        *len = ((base * scale * sr) as usize).max(1);
+        // -60 dB after rt60 seconds
+        *gain = 10.0f32.powf(-3.0 * *len as f32 / (rt60 * sr));
+      });
+  }
+
+  /// `damping` in the range `(0.0, 1.0)` darkens the tail.
+  #[inline]
+  pub fn process(&mut self, input: [f32; 2], size: f32, damping: f32) -> [f32; 2] {
+    if self.lines.is_empty() { return [0.0; 2] }
+    self.set_size(size.clamp(0.0, 1.0));
+    let damping = damping.clamp(0.0, 1.0) * 0.95;
+
+    // diffuse the input, four allpasses per channel
+    let (left, right) = self.diffusers.split_at_mut(4);
+    let l = left.iter_mut().fold(input[0], |s, ap| ap.process(s));
+    let r = right.iter_mut().fold(input[1], |s, ap| ap.process(s));
+
+    let mut taps = [0.0; LINES];
+    for (i, tap) in taps.iter_mut().enumerate() {
+      let out = self.lines[i].read(self.lengths[i]);
+      self.damp[i] = out * (1.0 - damping) + self.damp[i] * damping;
+      *tap = self.damp[i];
+    }
+    let out = [
+      taps.iter().step_by(2).sum::<f32>() * 0.5,
+      taps.iter().skip(1).step_by(2).sum::<f32>() * 0.5,
+    ];
+
+    hadamard(&mut taps);
+    for (i, tap) in taps.iter().enumerate() {
+      let input = if i % 2 == 0 { l } else { r };
+      self.lines[i].write(input + tap * self.gains[i]);
+    }
+    out
+  }
+
+  pub fn reset(&mut self) {
+    self.lines.iter_mut().for_each(Delay::clear);
+    self.diffusers.iter_mut().for_each(|ap| ap.delay.clear());
+    self.damp = [0.0; LINES];
+  }
+}
+
+/// Orthonormal 8 point Hadamard transform, mixes every line into every other line
+/// without changing the energy.
+#[inline]
+fn hadamard(x: &mut [f32; LINES]) {
+  let mut h = 1;
+  while h < LINES {
+    for i in (0..LINES).step_by(h * 2) {
+      for j in i..i + h {
+        let (a, b) = (x[j], x[j + h]);
+        x[j] = a + b;
+        x[j + h] = a - b;
+      }
+    }
+    h *= 2;
+  }
+  let norm = (LINES as f32).sqrt().recip();
+  x.iter_mut().for_each(|v| *v *= norm);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SR: f32 = 48000.0;
+
+  fn reverb() -> Reverb {
+    let mut r = Reverb::new();
+    r.set_samplerate(SR);
+    r
+  }
+
+  fn impulse_response(r: &mut Reverb, len: usize, size: f32, damping: f32) -> Vec<[f32; 2]> {
+    (0..len)
+      .map(|n| r.process(if n == 0 { [1.0, 1.0] } else { [0.0, 0.0] }, size, damping))
+      .collect()
+  }
+
+  #[test]
+  fn deterministic() {
+    let a = impulse_response(&mut reverb(), 48000, 0.7, 0.3);
+    let b = impulse_response(&mut reverb(), 48000, 0.7, 0.3);
+    assert!(a == b);
+    // and again after a reset
+    let mut r = reverb();
+    impulse_response(&mut r, 1000, 0.2, 0.8);
+    r.reset();
+    assert!(impulse_response(&mut r, 48000, 0.7, 0.3) == a);
+  }
+
+  #[test]
+  fn tail_decays() {
+    let ir = impulse_response(&mut reverb(), 4 * 48000, 0.3, 0.2);
+    let energy = |s: &[[f32; 2]]| s.iter().map(|[l, r]| l * l + r * r).sum::<f32>();
+    let early = energy(&ir[..48000]);
+    let late = energy(&ir[3 * 48000..]);
+    assert!(early > 0.0);
+    assert!(late < early * 1e-3);
+    assert!(ir.iter().all(|[l, r]| l.is_finite() && r.is_finite()));
+  }
+
+  #[test]
+  fn silence_in_silence_out() {
+    let mut r = reverb();
+    for _ in 0..10000 {
+      assert_eq!(r.process([0.0, 0.0], 1.0, 0.0), [0.0, 0.0]);
+    }
+  }
+
+  #[test]
+  fn hadamard_keeps_energy() {
+    let mut x = [1.0, -2.0, 3.0, 0.5, 0.0, 4.0, -1.0, 2.0];
+    let before: f32 = x.iter().map(|v| v * v).sum();
+    hadamard(&mut x);
+    let after: f32 = x.iter().map(|v| v * v).sum();
+    assert!((before - after).abs() < 1e-4);
+  }
+}
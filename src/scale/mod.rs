@@ -0,0 +1,114 @@
+use std::sync::Arc;
+use nih_plug::prelude::*;
+
+/// Scales the pitch of the grains can be snapped to.
+#[derive(Enum, PartialEq, Clone, Copy)]
+pub enum Scale {
+  Off,
+  Chromatic,
+  Major,
+  Minor,
+  Pentatonic,
+  #[name = "Whole tone"]
+  WholeTone,
+  #[name = "User"]
+  User,
+}
+
+impl Scale {
+  /// Pitch classes in the scale as a bit mask, bit `0` is the root.
+  #[inline]
+  pub fn mask(&self, user: u16) -> u16 {
+    match self {
+      Scale::Off        => { 0 },
+      Scale::Chromatic  => { 0b1111_1111_1111 },
+      Scale::Major      => { 0b1010_1011_0101 },
+      Scale::Minor      => { 0b0101_1010_1101 },
+      Scale::Pentatonic => { 0b0010_1001_0101 },
+      Scale::WholeTone  => { 0b0101_0101_0101 },
+      Scale::User       => { user },
+    }
+  }
+}
+
+/// Note names, for the root param.
+pub const NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
+/// One pitch class of the user defined scale.
+#[derive(Params)]
+pub struct ScaleNoteParams {
+  #[id = "scale-note"]
+  pub on: BoolParam,
+}
+
+impl ScaleNoteParams {
+  pub fn new(note: usize) -> Self {
+    Self {
+      on: BoolParam::new(format!("scale +{}", note), true),
+    }
+  }
+}
+
+/// Root of the scale, shown as a note name.
+pub fn root_param() -> IntParam {
+  IntParam::new("root", 0, IntRange::Linear { min: 0, max: 11 })
+    .with_value_to_string(Arc::new(|i| { NAMES[i.rem_euclid(12) as usize].to_string() }))
+    .with_string_to_value(Arc::new(|s| {
+      NAMES.iter().position(|n| n.eq_ignore_ascii_case(s.trim())).map(|i| i as i32)
+    }))
+}
+
+/// Snaps `semitones` to the nearest pitch in `mask`, counted from `root`.
+/// An empty mask leaves the pitch alone.
+#[inline]
+pub fn quantize(semitones: f32, mask: u16, root: i32) -> f32 {
+  if mask & 0b1111_1111_1111 == 0 { return semitones }
+  let relative = semitones - root as f32;
+  let base = relative.floor();
+  let mut nearest = base;
+  let mut distance = f32::MAX;
+  // the largest gap possible in a scale is eleven semitones
+  for k in -12..=12 {
+    let candidate = base + k as f32;
+    if mask & (1 << (candidate as i32).rem_euclid(12)) == 0 { continue }
+    let d = (candidate - relative).abs();
+    if d < distance {
+      nearest = candidate;
+      distance = d;
+    }
+  }
+  nearest + root as f32
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn snaps_to_major() {
+    let major = Scale::Major.mask(0);
+    assert_eq!(quantize(1.2, major, 0), 2.0);
+    assert_eq!(quantize(5.9, major, 0), 5.0);
+    assert_eq!(quantize(6.1, major, 0), 7.0);
+    assert_eq!(quantize(-1.4, major, 0), -1.0);
+    assert_eq!(quantize(13.0, major, 0), 12.0);
+  }
+
+  #[test]
+  fn follows_root() {
+    let major = Scale::Major.mask(0);
+    // D major has F# but not F
+    assert_eq!(quantize(5.2, major, 2), 6.0);
+    assert_eq!(quantize(2.3, major, 2), 2.0);
+  }
+
+  #[test]
+  fn masks() {
+    assert_eq!(Scale::Major.mask(0).count_ones(), 7);
+    assert_eq!(Scale::Minor.mask(0).count_ones(), 7);
+    assert_eq!(Scale::Pentatonic.mask(0).count_ones(), 5);
+    assert_eq!(Scale::WholeTone.mask(0).count_ones(), 6);
+    assert_eq!(quantize(0.4, Scale::Off.mask(0), 0), 0.4);
+    assert_eq!(quantize(4.4, Scale::User.mask(0b1000_0001), 0), 7.0);
+  }
+}
@@ -0,0 +1,145 @@
+use std::sync::Arc;
+use nih_plug::prelude::*;
+
+use crate::sync::Clock;
+
+/// Maximum number of steps in the sequencer.
+pub const STEPS: usize = 32;
+
+/// A single step, the offsets are added to the grains it triggers.
+#[derive(Params)]
+pub struct StepParams {
+  #[id = "step-on"]
+  pub on: BoolParam,
+  #[id = "step-probability"]
+  pub probability: FloatParam,
+  #[id = "step-position"]
+  pub position: FloatParam,
+  /// Offset in semitones.
+  #[id = "step-rate"]
+  pub rate: FloatParam,
+  #[id = "step-amp"]
+  pub amp: FloatParam,
+}
+
+impl StepParams {
+  pub fn new(step: usize) -> Self {
+    Self {
+      on: BoolParam::new(format!("step {} on", step), step % 4 == 1),
+      probability: FloatParam::new(
+        format!("step {} probability", step),
+        1.0,
+        FloatRange::Linear { min: 0.0, max: 1.0 }
+      )
+        .with_value_to_string(formatters::v2s_f32_percentage(0))
+        .with_string_to_value(formatters::s2v_f32_percentage())
+        .with_unit("%"),
+      position: FloatParam::new(
+        format!("step {} position", step),
+        0.0,
+        FloatRange::Linear { min: -0.5, max: 0.5 }
+      )
+        .with_value_to_string(Arc::new(|i| { format!("{:.2}", i) })),
+      rate: FloatParam::new(
+        format!("step {} rate", step),
+        0.0,
+        FloatRange::Linear { min: -24.0, max: 24.0 }
+      )
+        .with_step_size(1.0)
+        .with_unit(" st"),
+      amp: FloatParam::new(
+        format!("step {} amp", step),
+        0.0,
+        FloatRange::Linear { min: -1.0, max: 1.0 }
+      )
+        .with_value_to_string(Arc::new(|i| { format!("{:.2}", i) })),
+    }
+  }
+}
+
+/// Is `step` one of the `pulses` spread as evenly as possible over `length` steps,
+/// shifted `rotate` steps to the right.
+#[inline]
+pub fn euclidean(step: usize, pulses: usize, length: usize, rotate: usize) -> bool {
+  if length == 0 || pulses == 0 { return false }
+  let step = (step + length - rotate % length) % length;
+  // bresenham, same pattern as the bjorklund algorithm up to rotation
+  (step * pulses.min(length)) % length < pulses.min(length)
+}
+
+/// Steps through the pattern, following the song position of the host while it is
+/// playing and running freely at the host tempo while it is not.
+pub struct Sequencer {
+  clock: Clock,
+  beat: f64,
+}
+
+impl Sequencer {
+  pub fn new() -> Self {
+    Self { clock: Clock::new(), beat: 0.0 }
+  }
+
+  /// Returns the index of the step starting on this sample.
+  ///
+  /// `song` is the song position in quarter notes, `inc` the quarter notes per sample
+  /// and `step` the length of a step in quarter notes.
+  #[inline]
+  pub fn play(&mut self, song: Option<f64>, inc: f64, step: f64, length: usize) -> Option<usize> {
+    let beat = match song {
+      Some(beat) => { beat },
+      None       => { self.beat }
+    };
+    self.beat = beat + inc;
+    if length == 0 || self.clock.play(beat, inc, step, 0.0) < 1.0 { return None }
+    // nudged forward, the step might start a fraction of a sample early
+    Some(((beat + inc * 0.5) / step).floor().rem_euclid(length as f64) as usize)
+  }
+
+  #[inline]
+  pub fn reset(&mut self) {
+    self.clock.reset();
+    self.beat = 0.0;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn pattern(pulses: usize, length: usize, rotate: usize) -> String {
+    (0..length)
+      .map(|i| if euclidean(i, pulses, length, rotate) { 'x' } else { '.' })
+      .collect()
+  }
+
+  #[test]
+  fn euclidean_patterns() {
+    assert_eq!(pattern(4, 16, 0), "x...x...x...x...");
+    assert_eq!(pattern(3, 8, 0).matches('x').count(), 3);
+    assert_eq!(pattern(5, 16, 0).matches('x').count(), 5);
+    assert_eq!(pattern(0, 8, 0), "........");
+    assert_eq!(pattern(12, 8, 0), "xxxxxxxx");
+    assert_eq!(pattern(4, 16, 1), ".x...x...x...x..");
+  }
+
+  #[test]
+  fn steps_follow_song_position() {
+    let mut seq = Sequencer::new();
+    let inc = 0.01;
+    let steps: Vec<(usize, usize)> = (0..400)
+      .filter_map(|n| seq.play(Some(n as f64 * inc), inc, 0.25, 8).map(|s| (n, s)))
+      .collect();
+    assert_eq!(steps.len(), 16);
+    assert_eq!(steps[0], (0, 0));
+    assert_eq!(steps[1], (25, 1));
+    // wraps around after eight steps
+    assert_eq!(steps[8], (200, 0));
+  }
+
+  #[test]
+  fn runs_freely_without_song_position() {
+    let mut seq = Sequencer::new();
+    let steps: Vec<usize> = (0..400).filter_map(|_| seq.play(None, 0.01, 0.5, 16)).collect();
+    assert_eq!(steps, (0..8).collect::<Vec<_>>());
+  }
+}
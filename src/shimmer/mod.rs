@@ -0,0 +1,109 @@
+use std::f32::consts::PI;
+
+/// Length of the crossfade window in seconds.
+const WINDOW: f32 = 0.05;
+
+/// Delay line pitch shifter, two read heads sweep through a short window
+/// and are crossfaded so that the jump back is never heard.
+pub struct PitchShift {
+  buffer: Vec<f32>,
+  write: usize,
+  window: f32,
+  phase: f32,
+}
+
+impl PitchShift {
+  pub fn new() -> Self {
+    Self { buffer: Vec::new(), write: 0, window: 0.0, phase: 0.0 }
+  }
+
+  /// Allocates the delay line, call outside of the audio thread.
+  pub fn set_samplerate(&mut self, samplerate: f32) {
+    self.window = (WINDOW * samplerate).max(1.0);
+    self.buffer = vec![0.0; self.window as usize + 2];
+    self.write = 0;
+    self.phase = 0.0;
+  }
+
+  /// `ratio` is the playback rate, `2.0` is an octave up.
+  #[inline]
+  pub fn process(&mut self, input: f32, ratio: f32) -> f32 {
+    if self.buffer.is_empty() { return 0.0 }
+    self.buffer[self.write] = input;
+
+    let mut out = 0.0;
+    for offset in [0.0, 0.5] {
+      let phase = (self.phase + offset).fract();
+      // sin² and cos² of the two heads always add up to one
+      let gain = (PI * phase).sin().powi(2);
+      out += self.read(phase * self.window) * gain;
+    }
+
+    self.write = (self.write + 1) % self.buffer.len();
+    // a shorter delay each sample reads faster than it is written
+    self.phase = (self.phase + (1.0 - ratio) / self.window).rem_euclid(1.0);
+    out
+  }
+
+  /// Sample written `delay` samples ago, linearly interpolated.
+  #[inline]
+  fn read(&self, delay: f32) -> f32 {
+    let len = self.buffer.len();
+    let position = (self.write + len) as f32 - delay;
+    let i = position as usize;
+    let frac = position - i as f32;
+    let a = self.buffer[i % len];
+    let b = self.buffer[(i + 1) % len];
+    a + (b - a) * frac
+  }
+
+  pub fn reset(&mut self) {
+    self.buffer.fill(0.0);
+    self.phase = 0.0;
+  }
+}
+
+/// Semitones to playback rate.
+#[inline]
+pub fn ratio(semitones: i32) -> f32 {
+  (semitones as f32 / 12.0).exp2()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SR: f32 = 48000.0;
+
+  #[test]
+  fn unity_passes_dc() {
+    let mut p = PitchShift::new();
+    p.set_samplerate(SR);
+    let mut out = 0.0;
+    for _ in 0..48000 { out = p.process(1.0, 1.0); }
+    assert!((out - 1.0).abs() < 1e-4);
+  }
+
+  #[test]
+  fn octave_up_doubles_frequency() {
+    let mut p = PitchShift::new();
+    p.set_samplerate(SR);
+    let freq = 200.0;
+    let out: Vec<f32> = (0..48000)
+      .map(|n| p.process((2.0 * PI * freq * n as f32 / SR).sin(), ratio(12)))
+      .collect();
+    // count zero crossings over the last half second
+    let crossings = out[24000..]
+      .windows(2)
+      .filter(|w| w[0].signum() != w[1].signum())
+      .count();
+    assert!((crossings as f32 - 2.0 * freq).abs() < 2.0 * freq * 0.1);
+  }
+
+  #[test]
+  fn ratio_from_semitones() {
+    assert_eq!(ratio(0), 1.0);
+    assert_eq!(ratio(12), 2.0);
+    assert_eq!(ratio(-12), 0.5);
+  }
+}
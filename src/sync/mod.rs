@@ -0,0 +1,151 @@
+use nih_plug::prelude::*;
+
+/// Note divisions for tempo synced parameters.
+#[derive(Enum, PartialEq, Clone, Copy)]
+pub enum Division {
+  #[name = "4/1"]
+  FourBars,
+  #[name = "2/1"]
+  TwoBars,
+  #[name = "1/1"]
+  Whole,
+  #[name = "1/2"]
+  Half,
+  #[name = "1/4"]
+  Quarter,
+  #[name = "1/8"]
+  Eighth,
+  #[name = "1/16"]
+  Sixteenth,
+  #[name = "1/32"]
+  ThirtySecond,
+}
+
+#[derive(Enum, PartialEq, Clone, Copy)]
+pub enum Feel {
+  Straight,
+  Dotted,
+  Triplet,
+}
+
+impl Division {
+  /// Length of the division in quarter notes.
+  #[inline]
+  pub fn beats(&self, feel: Feel) -> f64 {
+    let beats = match self {
+      Division::FourBars     => { 16.0 },
+      Division::TwoBars      => { 8.0 },
+      Division::Whole        => { 4.0 },
+      Division::Half         => { 2.0 },
+      Division::Quarter      => { 1.0 },
+      Division::Eighth       => { 0.5 },
+      Division::Sixteenth    => { 0.25 },
+      Division::ThirtySecond => { 0.125 },
+    };
+    match feel {
+      Feel::Straight => { beats },
+      Feel::Dotted   => { beats * 1.5 },
+      Feel::Triplet  => { beats * 2.0 / 3.0 },
+    }
+  }
+
+  /// Length of the division in seconds, at `tempo` bpm.
+  #[inline]
+  pub fn seconds(&self, feel: Feel, tempo: f64) -> f32 {
+    (self.beats(feel) * 60.0 / tempo) as f32
+  }
+
+  /// Frequency of the division in Hz, at `tempo` bpm.
+  #[inline]
+  pub fn frequency(&self, feel: Feel, tempo: f64) -> f32 {
+    (tempo / (60.0 * self.beats(feel))) as f32
+  }
+}
+
+/// Trigger locked to the song position of the host.
+pub struct Clock {
+  last: Option<f64>,
+}
+
+impl Clock {
+  pub fn new() -> Self {
+    Self { last: None }
+  }
+
+  /// Returns `1.0` on the sample where `beat` passes a step.
+  ///
+  /// `beat` is the song position in quarter notes and `inc` the quarter notes per sample.
+  /// `step` is the length of a step in quarter notes, and `swing` in the range `(0.0, 1.0)`
+  /// pushes every second step up to half a step later.
+  #[inline]
+  pub fn play(&mut self, beat: f64, inc: f64, step: f64, swing: f64) -> f32 {
+    let prev = match self.last {
+      Some(last) if (beat - last).abs() <= step => { last },
+      // started, or the host jumped, look one sample back
+      _ => { beat - inc }
+    };
+    self.last = Some(beat);
+    if beat <= prev || step <= 0.0 { return 0.0 }
+
+    let pair = 2.0 * step;
+    let start = (prev / pair).floor() * pair;
+    let swung = step * (1.0 + swing.clamp(0.0, 1.0) * 0.5);
+    match [start, start + swung, start + pair].iter().any(|p| *p > prev && *p <= beat) {
+      true  => { 1.0 },
+      false => { 0.0 }
+    }
+  }
+
+  #[inline]
+  pub fn reset(&mut self) {
+    self.last = None;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn quarter_at_120() {
+    assert_eq!(Division::Quarter.frequency(Feel::Straight, 120.0), 2.0);
+    assert_eq!(Division::Quarter.seconds(Feel::Straight, 120.0), 0.5);
+  }
+
+  #[test]
+  fn dotted_and_triplet() {
+    assert_eq!(Division::Eighth.beats(Feel::Dotted), 0.75);
+    assert!((Division::Quarter.beats(Feel::Triplet) - 2.0 / 3.0).abs() < 1e-12);
+  }
+
+  /// Sample indices of the triggers over `beats` quarter notes, starting at `start`.
+  fn triggers(clock: &mut Clock, start: f64, beats: f64, step: f64, swing: f64) -> Vec<usize> {
+    let inc = 1.0 / 100.0;
+    (0..(beats / inc) as usize)
+      .filter(|n| clock.play(start + *n as f64 * inc, inc, step, swing) >= 1.0)
+      .collect()
+  }
+
+  #[test]
+  fn clock_follows_song_position() {
+    let mut clock = Clock::new();
+    assert_eq!(triggers(&mut clock, 0.0, 2.0, 0.5, 0.0), vec![0, 50, 100, 150]);
+    // starting in the middle of a step waits for the next one
+    clock.reset();
+    assert_eq!(triggers(&mut clock, 0.25, 1.0, 0.5, 0.0), vec![25, 75]);
+  }
+
+  #[test]
+  fn clock_swing_delays_every_second_step() {
+    let mut clock = Clock::new();
+    assert_eq!(triggers(&mut clock, 0.0, 2.0, 0.5, 1.0), vec![0, 75, 100, 175]);
+  }
+
+  #[test]
+  fn clock_restarts_after_a_jump() {
+    let mut clock = Clock::new();
+    triggers(&mut clock, 0.0, 1.5, 0.5, 0.0);
+    // looped back to the start
+    assert_eq!(triggers(&mut clock, 0.0, 0.5, 0.5, 0.0), vec![0]);
+  }
+}
@@ -0,0 +1,103 @@
+use crate::follower::Follower;
+
+/// Onset detector, compares a fast envelope against a slow one
+/// and fires when the fast envelope jumps above both the slow one and the threshold.
+pub struct Transient {
+  fast: Follower,
+  slow: Follower,
+  hold: u32,
+  samplerate: f32,
+}
+
+impl Transient {
+  pub fn new() -> Self {
+    Self {
+      fast: Follower::new(0.0005, 0.02),
+      slow: Follower::new(0.05, 0.2),
+      hold: 0,
+      samplerate: 0.0,
+    }
+  }
+
+  /// Returns `1.0` on every onset.
+  ///
+  /// `threshold` is the linear level an onset has to reach. `sensitivity` in the range
+  /// `(0.0, 1.0)` sets how far above the slow envelope the onset has to jump, and no new
+  /// onset is detected until `hold` seconds have passed.
+  #[inline]
+  pub fn play(&mut self, input: f32, threshold: f32, sensitivity: f32, hold: f32) -> f32 {
+    let fast = self.fast.play(input);
+    let slow = self.slow.play(input);
+    if self.hold > 0 {
+      self.hold -= 1;
+      return 0.0
+    }
+    // from 8 times the slow envelope down to just above it
+    let ratio = 1.0 + 7.0 * (1.0 - sensitivity.clamp(0.0, 1.0));
+    if fast < threshold || fast < slow * ratio { return 0.0 }
+    self.hold = (hold * self.samplerate) as u32;
+    1.0
+  }
+
+  #[inline]
+  pub fn set_samplerate(&mut self, samplerate: f32) {
+    self.samplerate = samplerate;
+    self.fast.set_samplerate(samplerate);
+    self.slow.set_samplerate(samplerate);
+  }
+
+  #[inline]
+  pub fn reset(&mut self) {
+    self.fast.reset();
+    self.slow.reset();
+    self.hold = 0;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SR: f32 = 48000.0;
+
+  /// Decaying clicks every `period` samples.
+  fn clicks(n: usize, period: usize) -> f32 {
+    let t = (n % period) as f32;
+    (-t / 200.0).exp() * if (n / 8) % 2 == 0 { 1.0 } else { -1.0 }
+  }
+
+  fn onsets(t: &mut Transient, threshold: f32, hold: f32, input: impl Fn(usize) -> f32) -> Vec<usize> {
+    (0..48000).filter(|n| t.play(input(*n), threshold, 0.5, hold) >= 1.0).collect()
+  }
+
+  fn detector() -> Transient {
+    let mut t = Transient::new();
+    t.set_samplerate(SR);
+    t
+  }
+
+  #[test]
+  fn one_onset_per_click() {
+    let found = onsets(&mut detector(), 0.1, 0.05, |n| clicks(n, 12000));
+    assert_eq!(found.len(), 4);
+    assert!(found.iter().zip([0, 12000, 24000, 36000]).all(|(a, b)| a - b < 48));
+  }
+
+  #[test]
+  fn below_threshold_is_ignored() {
+    assert!(onsets(&mut detector(), 0.5, 0.05, |n| clicks(n, 12000) * 0.25).is_empty());
+  }
+
+  #[test]
+  fn steady_tone_is_ignored() {
+    let sine = |n: usize| (2.0 * std::f32::consts::PI * 220.0 * n as f32 / SR).sin();
+    // only the start of the tone is an onset
+    assert_eq!(onsets(&mut detector(), 0.1, 0.05, sine).len(), 1);
+  }
+
+  #[test]
+  fn hold_blocks_retriggers() {
+    let found = onsets(&mut detector(), 0.1, 0.3, |n| clicks(n, 4800));
+    assert!(found.windows(2).all(|w| w[1] - w[0] >= 14400));
+  }
+}
@@ -0,0 +1,425 @@
+use std::path::Path;
+use std::sync::{
+  Arc,
+  Mutex,
+  RwLock,
+  RwLockReadGuard,
+  atomic::{AtomicU32, Ordering},
+};
+use nih_plug::prelude::*;
+use nih_plug::params::persist::PersistentField;
+use serde::{Deserialize, Serialize};
+
+use crate::scale::NAMES;
+
+/// A Scala scale, the pitches of the degrees above `1/1` in cents.
+/// The last degree is the period, usually the octave.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Scl {
+  pub description: String,
+  pub cents: Vec<f64>,
+}
+
+/// A Scala keyboard mapping.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Kbm {
+  pub first: i32,
+  pub last: i32,
+  /// Key where scale degree `0` is mapped.
+  pub middle: i32,
+  /// Key tuned to `frequency`.
+  pub reference: i32,
+  pub frequency: f64,
+  /// Scale degree of the formal octave of the mapping.
+  pub octave: i32,
+  /// Scale degree of every key in one repetition of the mapping, `None` is unmapped.
+  /// An empty mapping maps every key to the next degree.
+  pub map: Vec<Option<i32>>,
+}
+
+/// Lines of a Scala file, without the comments.
+fn lines(text: &str) -> impl Iterator<Item = &str> {
+  text.lines().filter(|line| !line.starts_with('!'))
+}
+
+/// First word of a line, the rest is free text.
+fn first_word(line: &str) -> &str {
+  line.split_whitespace().next().unwrap_or("")
+}
+
+/// Parses a pitch, either in cents if it has a period, or as a ratio.
+fn pitch(word: &str) -> Result<f64, String> {
+  let invalid = || format!("invalid pitch '{}'", word);
+  if word.contains('.') {
+    return word.parse::<f64>().map_err(|_| invalid())
+  }
+  let (n, d) = match word.split_once('/') {
+    Some((n, d)) => { (n, d) },
+    None         => { (word, "1") }
+  };
+  let n = n.parse::<u64>().map_err(|_| invalid())?;
+  let d = d.parse::<u64>().map_err(|_| invalid())?;
+  if n == 0 || d == 0 { return Err(invalid()) }
+  Ok(1200.0 * (n as f64 / d as f64).log2())
+}
+
+impl Scl {
+  pub fn parse(text: &str) -> Result<Self, String> {
+    let mut lines = lines(text);
+    let description = lines.next().ok_or("missing description")?.trim().to_string();
+    let count = lines
+      .next()
+      .and_then(|line| first_word(line).parse::<usize>().ok())
+      .ok_or("missing number of notes")?;
+    if count == 0 { return Err("scale has no notes".to_string()) }
+    let cents = lines
+      .take(count)
+      .map(|line| pitch(first_word(line)))
+      .collect::<Result<Vec<_>, _>>()?;
+    if cents.len() != count {
+      return Err(format!("expected {} notes, found {}", count, cents.len()))
+    }
+    Ok(Self { description, cents })
+  }
+
+  /// Twelve tone equal temperament.
+  pub fn equal() -> Self {
+    Self {
+      description: "12-TET".to_string(),
+      cents: (1..=12).map(|i| i as f64 * 100.0).collect(),
+    }
+  }
+
+  /// Pitch of a scale degree, in cents above degree `0`.
+  #[inline]
+  pub fn cents(&self, degree: i32) -> f64 {
+    let len = self.cents.len() as i32;
+    let period = self.cents[self.cents.len() - 1];
+    let step = match degree.rem_euclid(len) {
+      0 => { 0.0 },
+      d => { self.cents[d as usize - 1] }
+    };
+    degree.div_euclid(len) as f64 * period + step
+  }
+}
+
+impl Kbm {
+  pub fn parse(text: &str) -> Result<Self, String> {
+    let mut words = lines(text).map(first_word);
+    let mut next = |name: &str| words.next().ok_or(format!("missing {}", name));
+    let int = |word: &str, name: &str| word.parse::<i32>().map_err(|_| format!("invalid {}", name));
+    let size = int(next("map size")?, "map size")?.max(0) as usize;
+    let first = int(next("first note")?, "first note")?;
+    let last = int(next("last note")?, "last note")?;
+    let middle = int(next("middle note")?, "middle note")?;
+    let reference = int(next("reference note")?, "reference note")?;
+    let frequency = next("reference frequency")?
+      .parse::<f64>()
+      .ok()
+      .filter(|f| *f > 0.0)
+      .ok_or("invalid reference frequency")?;
+    let octave = int(next("octave degree")?, "octave degree")?;
+    // missing keys at the end of the mapping are left unmapped
+    let map = (0..size)
+      .map(|_| match words.next() {
+        Some("x") | None => { Ok(None) },
+        Some(word)       => { int(word, "mapping").map(Some) }
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+    Ok(Self { first, last, middle, reference, frequency, octave, map })
+  }
+
+  /// Scale degree of `key`, counted from the middle note.
+  #[inline]
+  pub fn degree(&self, key: i32, scale_len: usize) -> Option<i32> {
+    if key < self.first || key > self.last { return None }
+    let i = key - self.middle;
+    if self.map.is_empty() { return Some(i) }
+    let len = self.map.len() as i32;
+    let octave = if self.octave > 0 { self.octave } else { scale_len as i32 };
+    self.map[i.rem_euclid(len) as usize].map(|d| d + i.div_euclid(len) * octave)
+  }
+}
+
+/// Frequency of `key` in twelve tone equal temperament.
+#[inline]
+pub fn equal_frequency(key: i32, a4: f32) -> f32 {
+  a4 * ((key - 69) as f32 / 12.0).exp2()
+}
+
+/// Source text of the loaded files, this is what is stored in the plugin state.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+pub struct TuningSource {
+  pub scl: Option<String>,
+  pub kbm: Option<String>,
+}
+
+/// The loaded scale and mapping, twelve tone equal temperament when no files are loaded.
+pub struct TuningState {
+  source: TuningSource,
+  scl: Scl,
+  kbm: Option<Kbm>,
+}
+
+impl Default for TuningState {
+  fn default() -> Self {
+    Self { source: TuningSource::default(), scl: Scl::equal(), kbm: None }
+  }
+}
+
+impl TuningState {
+  /// Invalid files in the state are skipped.
+  fn from_source(source: TuningSource) -> Self {
+    let scl = source.scl.as_deref().and_then(|s| Scl::parse(s).ok());
+    let kbm = source.kbm.as_deref().and_then(|s| Kbm::parse(s).ok());
+    Self {
+      source: TuningSource {
+        scl: scl.as_ref().and(source.scl),
+        kbm: kbm.as_ref().and(source.kbm),
+      },
+      scl: scl.unwrap_or_else(Scl::equal),
+      kbm,
+    }
+  }
+
+  pub fn description(&self) -> &str {
+    &self.scl.description
+  }
+
+  /// Frequency of `key`, or `None` if it is unmapped. Without a mapping, degree `0` of the
+  /// scale is on `root_key`, which keeps its equal tempered pitch from `a4`. The reference
+  /// frequency of a mapping is transposed by `a4` relative to 440 Hz.
+  #[inline]
+  pub fn frequency(&self, key: i32, a4: f32, root_key: i32) -> Option<f32> {
+    let len = self.scl.cents.len();
+    let (degree, reference, frequency) = match &self.kbm {
+      Some(kbm) => {
+        let frequency = kbm.frequency * a4 as f64 / 440.0;
+        (kbm.degree(key, len)?, kbm.degree(kbm.reference, len).unwrap_or(0), frequency)
+      },
+      None => {
+        (key - root_key, 0, equal_frequency(root_key, a4) as f64)
+      }
+    };
+    let cents = self.scl.cents(degree) - self.scl.cents(reference);
+    Some((frequency * (cents / 1200.0).exp2()) as f32)
+  }
+
+  /// Playback rate of every key. With the pitch of the recording as `source`, every key
+  /// plays at its own frequency, otherwise the recording is taken to be `root_key` at
+  /// A4 = 440 Hz, so `a4` detunes every key. Unmapped keys get a rate of `0.0`. Does not allocate.
+  pub fn rates(&self, a4: f32, root_key: i32, source: Option<f32>, rates: &mut [f32]) {
+    let root = source.unwrap_or_else(|| equal_frequency(root_key, 440.0));
+    for (key, rate) in rates.iter_mut().enumerate() {
+      *rate = self.frequency(key as i32, a4, root_key).map_or(0.0, |f| f / root);
+    }
+  }
+}
+
+/// Scala tuning, persisted in the plugin state.
+///
+/// Files are loaded by a background task, and the audio thread rebuilds its rate table
+/// whenever `version` changes.
+pub struct Tuning {
+  state: RwLock<TuningState>,
+  version: AtomicU32,
+  /// Why the last file failed to load, cleared by the next load.
+  error: Mutex<Option<String>>,
+}
+
+impl Default for Tuning {
+  fn default() -> Self {
+    Self {
+      state: RwLock::new(TuningState::default()),
+      version: AtomicU32::new(1),
+      error: Mutex::new(None),
+    }
+  }
+}
+
+impl Tuning {
+  #[inline]
+  pub fn version(&self) -> u32 {
+    self.version.load(Ordering::Acquire)
+  }
+
+  /// Does not block, returns `None` while a file is being loaded.
+  #[inline]
+  pub fn try_state(&self) -> Option<RwLockReadGuard<TuningState>> {
+    self.state.try_read().ok()
+  }
+
+  pub fn state(&self) -> RwLockReadGuard<TuningState> {
+    self.state.read().unwrap()
+  }
+
+  fn update(&self, f: impl FnOnce(&mut TuningSource)) {
+    let mut state = self.state.write().unwrap();
+    let mut source = state.source.clone();
+    f(&mut source);
+    *state = TuningState::from_source(source);
+    self.version.fetch_add(1, Ordering::Release);
+  }
+
+  /// Loads a `.scl` scale or a `.kbm` keyboard mapping, picked by the extension.
+  pub fn load(&self, path: &Path) -> Result<(), String> {
+    let result = self.read(path);
+    *self.error.lock().unwrap() = result.as_ref().err().cloned();
+    result
+  }
+
+  fn read(&self, path: &Path) -> Result<(), String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let extension = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+      Some("scl") => {
+        Scl::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        self.update(|source| source.scl = Some(text));
+      },
+      Some("kbm") => {
+        Kbm::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        self.update(|source| source.kbm = Some(text));
+      },
+      _ => { return Err(format!("{}: not a .scl or .kbm file", path.display())) }
+    }
+    Ok(())
+  }
+
+  /// Back to twelve tone equal temperament.
+  pub fn clear(&self) {
+    *self.error.lock().unwrap() = None;
+    self.update(|source| *source = TuningSource::default());
+  }
+
+  /// The last load error, or the description of the loaded scale.
+  pub fn status(&self) -> String {
+    if let Some(error) = self.error.lock().unwrap().clone() { return error }
+    let state = self.state();
+    match state.kbm {
+      Some(_) => { format!("{}, mapped", state.description()) },
+      None    => { state.description().to_string() }
+    }
+  }
+}
+
+impl<'a> PersistentField<'a, TuningSource> for Tuning {
+  fn set(&self, new_value: TuningSource) {
+    self.update(|source| *source = new_value);
+  }
+
+  fn map<F, R>(&self, f: F) -> R
+  where
+    F: Fn(&TuningSource) -> R,
+  {
+    f(&self.state.read().unwrap().source)
+  }
+}
+
+/// Key that plays the recording at its own pitch, shown as a note name.
+pub fn key_param() -> IntParam {
+  IntParam::new("root key", 60, IntRange::Linear { min: 0, max: 127 })
+    .with_value_to_string(Arc::new(|k| {
+      format!("{}{}", NAMES[k.rem_euclid(12) as usize], k.div_euclid(12) - 1)
+    }))
+    .with_string_to_value(Arc::new(|s| {
+      let s = s.trim();
+      let split = s.find(|c: char| c.is_ascii_digit() || c == '-')?;
+      let (name, octave) = s.split_at(split);
+      let note = NAMES.iter().position(|n| n.eq_ignore_ascii_case(name))? as i32;
+      Some(note + (octave.parse::<i32>().ok()? + 1) * 12)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const PYTHAGOREAN: &str = "! pyth.scl\n!\nPythagorean pentatonic\n 5\n!\n 9/8\n 81/64\n 3/2\n 27/16\n 2/1\n";
+
+  #[test]
+  fn parses_scl() {
+    let scl = Scl::parse(PYTHAGOREAN).unwrap();
+    assert_eq!(scl.description, "Pythagorean pentatonic");
+    assert_eq!(scl.cents.len(), 5);
+    assert!((scl.cents[2] - 701.955).abs() < 0.001);
+    assert_eq!(scl.cents(5), 1200.0);
+    assert!((scl.cents(-1) - (27.0f64 / 32.0).log2() * 1200.0).abs() < 0.001);
+    let cents = Scl::parse("cents\n2\n150.0 quarter\n1200.\n").unwrap();
+    assert_eq!(cents.cents, vec![150.0, 1200.0]);
+    assert!(Scl::parse("short\n3\n100.0\n").is_err());
+    assert!(Scl::parse("bad\n1\n3/0\n").is_err());
+  }
+
+  #[test]
+  fn equal_tempered_rates() {
+    let state = TuningState::default();
+    let mut rates = [0.0; 128];
+    state.rates(440.0, 60, None, &mut rates);
+    assert_eq!(rates[60], 1.0);
+    assert!((rates[72] - 2.0).abs() < 1e-6);
+    assert!((rates[67] - 1.4983).abs() < 1e-4);
+    assert!((state.frequency(69, 440.0, 60).unwrap() - 440.0).abs() < 1e-3);
+    assert!((state.frequency(69, 432.0, 60).unwrap() - 432.0).abs() < 1e-3);
+    // a recording of A3 played from A4 is an octave up
+    state.rates(440.0, 60, Some(220.0), &mut rates);
+    assert!((rates[69] - 2.0).abs() < 1e-5);
+    assert!((rates[57] - 1.0).abs() < 1e-5);
+  }
+
+  #[test]
+  fn scale_starts_on_root_key() {
+    let state = TuningState::from_source(TuningSource { scl: Some(PYTHAGOREAN.to_string()), kbm: None });
+    let mut rates = [0.0; 128];
+    state.rates(440.0, 62, None, &mut rates);
+    assert_eq!(rates[62], 1.0);
+    assert!((rates[65] - 1.5).abs() < 1e-6);
+    assert!((rates[67] - 2.0).abs() < 1e-6);
+    assert!((rates[57] - 0.5).abs() < 1e-6);
+  }
+
+  #[test]
+  fn keyboard_mapping() {
+    // white keys only, A4 at 432 Hz
+    let kbm = "! white.kbm\n12\n0\n127\n60\n69\n432.0\n7\n0\nx\n1\nx\n2\n3\nx\n4\nx\n5\nx\n6\n";
+    let major = "just major\n7\n9/8\n5/4\n4/3\n3/2\n5/3\n15/8\n2/1\n";
+    let state = TuningState::from_source(TuningSource {
+      scl: Some(major.to_string()),
+      kbm: Some(kbm.to_string()),
+    });
+    assert_eq!(state.frequency(61, 440.0, 60), None);
+    assert!((state.frequency(69, 440.0, 60).unwrap() - 432.0).abs() < 1e-3);
+    assert!((state.frequency(72, 440.0, 60).unwrap() / state.frequency(60, 440.0, 60).unwrap() - 2.0).abs() < 1e-5);
+    let mut rates = [1.0; 128];
+    state.rates(440.0, 60, None, &mut rates);
+    assert_eq!(rates[61], 0.0);
+    assert!((rates[67] / rates[60] - 1.5).abs() < 1e-5);
+    // A4 transposes the mapping
+    assert!((state.frequency(69, 432.0, 60).unwrap() - 424.145).abs() < 1e-2);
+  }
+
+  #[test]
+  fn a4_detunes_rates() {
+    let state = TuningState::default();
+    let (mut at440, mut at432) = ([0.0; 128], [0.0; 128]);
+    state.rates(440.0, 60, None, &mut at440);
+    state.rates(432.0, 60, None, &mut at432);
+    assert!(at440.iter().zip(at432.iter()).all(|(a, b)| (b / a - 432.0 / 440.0).abs() < 1e-5));
+  }
+
+  #[test]
+  fn status_shows_load_errors() {
+    let tuning = Tuning::default();
+    assert_eq!(tuning.status(), "12-TET");
+    assert!(tuning.load(Path::new("missing.scl")).is_err());
+    assert!(tuning.status().starts_with("missing.scl: "));
+    tuning.clear();
+    assert_eq!(tuning.status(), "12-TET");
+  }
+
+  #[test]
+  fn invalid_state_is_skipped() {
+    let state = TuningState::from_source(TuningSource { scl: Some("broken".to_string()), kbm: None });
+    assert_eq!(state.source, TuningSource::default());
+    assert_eq!(state.scl, Scl::equal());
+  }
+}